@@ -0,0 +1,72 @@
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum AdsDataType {
+    Void = 0,
+    Bit = 33,
+    Int8 = 16,
+    UInt8 = 17,
+    Int16 = 2,
+    UInt16 = 18,
+    Int32 = 3,
+    UInt32 = 19,
+    Int64 = 20,
+    UInt64 = 21,
+    Real32 = 4,
+    Real64 = 5,
+    Real80 = 32,
+    String = 30,
+    WString = 31,
+    MaxTypes = 34,
+    BigType = 65,
+}
+
+impl TryFrom<u32> for AdsDataType {
+    type Error = ();
+
+    fn try_from(v: u32) -> Result<Self, Self::Error> {
+        match v {
+            x if x == AdsDataType::Void as u32 => Ok(AdsDataType::Void),
+            x if x == AdsDataType::Bit as u32 => Ok(AdsDataType::Bit),
+            x if x == AdsDataType::Int8 as u32 => Ok(AdsDataType::Int8),
+            x if x == AdsDataType::UInt8 as u32 => Ok(AdsDataType::UInt8),
+            x if x == AdsDataType::Int16 as u32 => Ok(AdsDataType::Int16),
+            x if x == AdsDataType::UInt16 as u32 => Ok(AdsDataType::UInt16),
+            x if x == AdsDataType::Int32 as u32 => Ok(AdsDataType::Int32),
+            x if x == AdsDataType::UInt32 as u32 => Ok(AdsDataType::UInt32),
+            x if x == AdsDataType::Int64 as u32 => Ok(AdsDataType::Int64),
+            x if x == AdsDataType::UInt64 as u32 => Ok(AdsDataType::UInt64),
+            x if x == AdsDataType::Real32 as u32 => Ok(AdsDataType::Real32),
+            x if x == AdsDataType::Real64 as u32 => Ok(AdsDataType::Real64),
+            x if x == AdsDataType::Real80 as u32 => Ok(AdsDataType::Real80),
+            x if x == AdsDataType::String as u32 => Ok(AdsDataType::String),
+            x if x == AdsDataType::WString as u32 => Ok(AdsDataType::WString),
+            x if x == AdsDataType::MaxTypes as u32 => Ok(AdsDataType::MaxTypes),
+            x if x == AdsDataType::BigType as u32 => Ok(AdsDataType::BigType),
+            _ => Err(()),
+        }
+    }
+}
+
+impl AdsDataType {
+    /// Whether values of this type are two's complement signed integers.
+    pub fn is_signed(self) -> bool {
+        matches!(
+            self,
+            AdsDataType::Int8 | AdsDataType::Int16 | AdsDataType::Int32 | AdsDataType::Int64
+        )
+    }
+    /// Size in bytes of a single value of a primitive type, `None` for strings and compound types.
+    pub fn fixed_size(self) -> Option<usize> {
+        match self {
+            AdsDataType::Bit | AdsDataType::Int8 | AdsDataType::UInt8 => Some(1),
+            AdsDataType::Int16 | AdsDataType::UInt16 => Some(2),
+            AdsDataType::Int32 | AdsDataType::UInt32 | AdsDataType::Real32 => Some(4),
+            AdsDataType::Int64 | AdsDataType::UInt64 | AdsDataType::Real64 => Some(8),
+            AdsDataType::Real80 => Some(10),
+            AdsDataType::Void
+            | AdsDataType::String
+            | AdsDataType::WString
+            | AdsDataType::MaxTypes
+            | AdsDataType::BigType => None,
+        }
+    }
+}
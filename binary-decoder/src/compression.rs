@@ -0,0 +1,133 @@
+use crate::error::DecodeError;
+use crate::reader::Reader;
+
+/// Longest literal or repeated run a single run length control byte can describe.
+const MAX_RUN: usize = 128;
+
+/// Compression applied to the sample payload of a data stream, as selected by the three bit
+/// [`compression_method`](crate::DataStreamFlags::compression_method) field.
+///
+/// The run length encoding is the PackBits scheme: a control byte `n` in `0..=127` is followed
+/// by `n + 1` literal bytes, a control byte in `129..=255` by a single byte that is repeated
+/// `257 - n` times. A control byte of 128 is skipped.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum Compression {
+    None = 0,
+    RunLength = 1,
+}
+
+impl TryFrom<u8> for Compression {
+    type Error = ();
+
+    fn try_from(v: u8) -> Result<Self, Self::Error> {
+        match v {
+            x if x == Compression::None as u8 => Ok(Compression::None),
+            x if x == Compression::RunLength as u8 => Ok(Compression::RunLength),
+            _ => Err(()),
+        }
+    }
+}
+
+impl Compression {
+    /// Compresses a sample payload.
+    pub fn compress(self, data: &[u8]) -> Vec<u8> {
+        match self {
+            Compression::None => data.to_vec(),
+            Compression::RunLength => compress_run_length(data),
+        }
+    }
+
+    /// Restores a sample payload compressed with this method. Fails with
+    /// [`DecodeError::LengthMismatch`] as soon as the payload would grow past `max_len` bytes,
+    /// as a few bytes of run length encoding describe a lot of output.
+    pub fn decompress(self, data: &[u8], max_len: usize) -> Result<Vec<u8>, DecodeError> {
+        let mut stream = Reader::new(data);
+        self.decompress_from(&mut stream, max_len)
+    }
+
+    /// Decompresses everything left in `stream`, into at most `max_len` bytes.
+    pub(crate) fn decompress_from(
+        self,
+        stream: &mut Reader,
+        max_len: usize,
+    ) -> Result<Vec<u8>, DecodeError> {
+        match self {
+            Compression::None => {
+                let len = stream.rest().len();
+                check_len(stream.offset(), len, max_len)?;
+                Ok(stream.take(len)?.to_vec())
+            }
+            Compression::RunLength => decompress_run_length(stream, max_len),
+        }
+    }
+}
+
+fn compress_run_length(data: &[u8]) -> Vec<u8> {
+    let mut out = Vec::with_capacity(data.len() + data.len() / MAX_RUN + 1);
+    let mut literal_start = 0;
+    let mut i = 0;
+
+    while i < data.len() {
+        let run = data[i..]
+            .iter()
+            .take(MAX_RUN)
+            .take_while(|&&b| b == data[i])
+            .count();
+
+        // A run of two costs as much as two literals, and breaking up a literal costs more
+        if run >= 3 {
+            push_literals(&mut out, &data[literal_start..i]);
+            out.push((257 - run) as u8);
+            out.push(data[i]);
+            literal_start = i + run;
+        }
+        i += run;
+    }
+    push_literals(&mut out, &data[literal_start..]);
+
+    out
+}
+
+fn push_literals(out: &mut Vec<u8>, literals: &[u8]) {
+    for chunk in literals.chunks(MAX_RUN) {
+        out.push((chunk.len() - 1) as u8);
+        out.extend_from_slice(chunk);
+    }
+}
+
+fn decompress_run_length(stream: &mut Reader, max_len: usize) -> Result<Vec<u8>, DecodeError> {
+    let mut out = Vec::with_capacity(stream.rest().len().min(max_len));
+
+    while !stream.rest().is_empty() {
+        let offset = stream.offset();
+        match stream.get_u8()? {
+            128 => {}
+            control @ 0..=127 => {
+                let literals = stream.take(control as usize + 1)?;
+                check_len(offset, out.len() + literals.len(), max_len)?;
+                out.extend_from_slice(literals);
+            }
+            control => {
+                let byte = stream.get_u8()?;
+                let len = out.len() + 257 - control as usize;
+                check_len(offset, len, max_len)?;
+                out.resize(len, byte);
+            }
+        }
+    }
+
+    Ok(out)
+}
+
+/// Fails if the payload would grow to `len` bytes, past `max_len`, at the control byte at
+/// `offset`.
+fn check_len(offset: usize, len: usize, max_len: usize) -> Result<(), DecodeError> {
+    if len > max_len {
+        return Err(DecodeError::LengthMismatch {
+            offset,
+            expected: max_len,
+            actual: len,
+        });
+    }
+    Ok(())
+}
@@ -0,0 +1,284 @@
+use bitflags::bitflags;
+use bytes::{Buf, Bytes};
+use uuid::Uuid;
+
+use crate::compression::Compression;
+use crate::error::DecodeError;
+use crate::reader::Reader;
+use crate::symbol_stream::Version;
+use crate::timestamp::Timestamp;
+
+/// A decoded data stream message, as published on the `Bin/Tx/Data` topic.
+///
+/// The layout of each sample is not part of the message; it is described by the symbol stream
+/// whose [`layout`](crate::SymbolStreamHeader::layout) matches [`DataStreamHeader::layout`].
+///
+/// The samples are kept in the decompressed payload, one every
+/// [`sample_len`](DataStreamHeader::sample_len) bytes, and split off as they are read.
+#[derive(Debug, Clone)]
+pub struct DataStream {
+    pub header: DataStreamHeader,
+    payload: Bytes,
+}
+
+#[derive(Debug, Copy, Clone)]
+pub struct DataStreamHeader {
+    pub version: Version,
+    pub header_len: u8,
+    /// Size of the header in front of each sample, zero when samples carry no timestamp.
+    pub sample_header_len: u8,
+    /// Size of the data portion of each sample.
+    pub sample_data_len: usize,
+    /// Time between two samples, in 100 ns units.
+    pub cycle_time: u32,
+    pub flags: DataStreamFlags,
+    pub layout: Uuid,
+    /// Only present from version 1.1 on.
+    pub sample_count: Option<u64>,
+    /// Raw time of the first sample, only present from version 1.1 on. See
+    /// [`start`](DataStreamHeader::start).
+    pub start_time: Option<u64>,
+    /// Raw time of the last sample, only present from version 1.1 on. See
+    /// [`stop`](DataStreamHeader::stop).
+    pub stop_time: Option<u64>,
+}
+
+impl DataStreamHeader {
+    /// Size of one sample including its sample header.
+    pub fn sample_len(&self) -> usize {
+        self.sample_header_len as usize + self.sample_data_len
+    }
+
+    /// Interprets a raw time of this stream in the unit selected by its flags.
+    pub fn timestamp(&self, raw: u64) -> Timestamp {
+        if self.flags.contains(DataStreamFlags::DC_TIME) {
+            Timestamp::DcTime(raw)
+        } else {
+            Timestamp::FileTime(raw)
+        }
+    }
+
+    /// Time of the first sample, when the header carries timestamps.
+    pub fn start(&self) -> Option<Timestamp> {
+        self.head_timestamp(self.start_time)
+    }
+
+    /// Time of the last sample, when the header carries timestamps.
+    pub fn stop(&self) -> Option<Timestamp> {
+        self.head_timestamp(self.stop_time)
+    }
+
+    fn head_timestamp(&self, raw: Option<u64>) -> Option<Timestamp> {
+        match raw {
+            Some(raw) if self.flags.contains(DataStreamFlags::HEAD_TIMESTAMP) => {
+                Some(self.timestamp(raw))
+            }
+            _ => None,
+        }
+    }
+}
+
+bitflags! {
+    /// Flags in the data stream header.
+    #[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+    pub struct DataStreamFlags: u32 {
+        const HEAD_TIMESTAMP = 1 << 0;
+        const SAMPLE_TIMESTAMP = 1 << 1;
+        const DC_TIME = 1 << 2;
+        const COMPRESSION_METHOD = 0b111 << 4;
+
+        // Keep bits we do not know about yet
+        const _ = !0;
+    }
+}
+
+impl DataStreamFlags {
+    /// The three bit compression method field.
+    pub fn compression_method(&self) -> u8 {
+        ((self.bits() & DataStreamFlags::COMPRESSION_METHOD.bits()) >> 4) as u8
+    }
+
+    /// The compression applied to the sample payload.
+    pub fn compression(&self) -> Result<Compression, DecodeError> {
+        let method = self.compression_method();
+        Compression::try_from(method).map_err(|_| DecodeError::UnsupportedCompression { method })
+    }
+}
+
+impl From<u32> for DataStreamFlags {
+    fn from(v: u32) -> Self {
+        DataStreamFlags::from_bits_retain(v)
+    }
+}
+
+/// One sample of a data stream, still in its binary form.
+#[derive(Debug, Clone)]
+pub struct RawSample {
+    /// Time of the sample: from the sample header when the stream carries per-sample
+    /// timestamps, otherwise counted on from the header start time in steps of the cycle time.
+    pub timestamp: Option<Timestamp>,
+    /// The sample data, laid out as described by the matching symbol stream.
+    pub data: Bytes,
+}
+
+impl DataStream {
+    /// Largest sample payload [`parse`](DataStream::parse) restores. A 1.1 header bounds it
+    /// further to its sample count times the sample size; a 1.0 header does not say.
+    pub const MAX_PAYLOAD_LEN: usize = 64 << 20;
+
+    /// Decodes a data stream message and splits its payload into samples.
+    pub fn parse(data: &[u8]) -> Result<DataStream, DecodeError> {
+        DataStream::parse_with_limit(data, DataStream::MAX_PAYLOAD_LEN)
+    }
+
+    /// Like [`parse`](DataStream::parse), restoring at most `max_payload_len` bytes of samples.
+    pub fn parse_with_limit(
+        data: &[u8],
+        max_payload_len: usize,
+    ) -> Result<DataStream, DecodeError> {
+        let mut stream = Reader::new(data);
+        let header = parse_header(&mut stream)?;
+        let payload = parse_payload(&header, &mut stream, max_payload_len)?;
+
+        Ok(DataStream { header, payload })
+    }
+
+    /// Number of samples in the message.
+    pub fn len(&self) -> usize {
+        match self.header.sample_len() {
+            0 => 0,
+            sample_len => self.payload.len() / sample_len,
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// The sample at `index`, if there are that many.
+    pub fn get(&self, index: usize) -> Option<RawSample> {
+        (index < self.len()).then(|| self.sample(index))
+    }
+
+    /// The samples in the order they were taken.
+    pub fn samples(&self) -> impl ExactSizeIterator<Item = RawSample> + DoubleEndedIterator + '_ {
+        (0..self.len()).map(|index| self.sample(index))
+    }
+
+    fn sample(&self, index: usize) -> RawSample {
+        let header = &self.header;
+        let start = index * header.sample_len();
+        let data_start = start + header.sample_header_len as usize;
+
+        let timestamp = if header.flags.contains(DataStreamFlags::SAMPLE_TIMESTAMP) {
+            Some(header.timestamp((&self.payload[start..start + 8]).get_u64_le()))
+        } else {
+            header
+                .start()
+                .map(|start| start.add_ticks(index as u64 * u64::from(header.cycle_time)))
+        };
+
+        RawSample {
+            timestamp,
+            data: self
+                .payload
+                .slice(data_start..data_start + header.sample_data_len),
+        }
+    }
+}
+
+fn parse_header(stream: &mut Reader) -> Result<DataStreamHeader, DecodeError> {
+    let start = stream.offset();
+
+    let major = stream.get_u8()?;
+    let minor = stream.get_u8()?;
+    let version = Version { major, minor };
+    if (major, minor) != (1, 0) && (major, minor) != (1, 1) {
+        return Err(DecodeError::UnsupportedVersion { major, minor });
+    }
+
+    let header_len = stream.get_u8()?;
+    let sample_header_len = stream.get_u8()?;
+    let sample_data_len = stream.get_u32_le()? as usize;
+    let cycle_time = stream.get_u32_le()?;
+    let flags = DataStreamFlags::from(stream.get_u32_le()?);
+    let layout = stream.get_guid()?;
+
+    let (sample_count, start_time, stop_time) = if minor >= 1 {
+        let sample_count = stream.get_u64_le()?;
+        let start_time = stream.get_u64_le()?;
+        let stop_time = stream.get_u64_le()?;
+        (Some(sample_count), Some(start_time), Some(stop_time))
+    } else {
+        (None, None, None)
+    };
+
+    // Skip anything a newer header revision may have appended
+    let consumed = stream.offset() - start;
+    let padding =
+        (header_len as usize)
+            .checked_sub(consumed)
+            .ok_or(DecodeError::LengthMismatch {
+                offset: start + 2,
+                expected: consumed,
+                actual: header_len as usize,
+            })?;
+    stream.advance(padding)?;
+
+    Ok(DataStreamHeader {
+        version,
+        header_len,
+        sample_header_len,
+        sample_data_len,
+        cycle_time,
+        flags,
+        layout,
+        sample_count,
+        start_time,
+        stop_time,
+    })
+}
+
+/// Decompresses the samples, checking that they fill the payload exactly.
+fn parse_payload(
+    header: &DataStreamHeader,
+    stream: &mut Reader,
+    max_payload_len: usize,
+) -> Result<Bytes, DecodeError> {
+    let offset = stream.offset();
+    let sample_len = header.sample_len();
+    // The timestamp of a sample takes up the first eight bytes of its header
+    if header.flags.contains(DataStreamFlags::SAMPLE_TIMESTAMP) && header.sample_header_len < 8 {
+        return Err(DecodeError::LengthMismatch {
+            offset: 3,
+            expected: 8,
+            actual: header.sample_header_len as usize,
+        });
+    }
+
+    // Known before decompressing for 1.1 headers, so that a payload cannot grow past it
+    let expected = header.sample_count.map(|count| {
+        usize::try_from(count)
+            .ok()
+            .and_then(|count| count.checked_mul(sample_len))
+            .unwrap_or(usize::MAX)
+    });
+    let max_len = expected.map_or(max_payload_len, |len| len.min(max_payload_len));
+    let compression = header.flags.compression()?;
+    let payload = Bytes::from(compression.decompress_from(stream, max_len)?);
+
+    // 1.0 headers do not carry a sample count, so it is implied by the payload size
+    let expected = expected.unwrap_or(match sample_len {
+        0 => 0,
+        _ => payload.len() - payload.len() % sample_len,
+    });
+    if payload.len() != expected {
+        return Err(DecodeError::LengthMismatch {
+            offset,
+            expected,
+            actual: payload.len(),
+        });
+    }
+
+    Ok(payload)
+}
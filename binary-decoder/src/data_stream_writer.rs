@@ -0,0 +1,216 @@
+use std::sync::Arc;
+
+use uuid::Uuid;
+
+use crate::compression::Compression;
+use crate::data_stream::DataStreamFlags;
+use crate::error::EncodeError;
+use crate::sample::{Field, Sample, TypeIndex};
+use crate::symbol_stream::{SymbolStream, Version};
+use crate::text::CodePage;
+use crate::timestamp::Timestamp;
+use crate::value::PlcValue;
+use crate::writer::Writer;
+
+/// Encodes samples as data stream messages, laid out as described by a symbol stream. The
+/// counterpart of [`SampleDecoder`](crate::SampleDecoder).
+///
+/// By default the writer produces 1.1 headers carrying the times of the first and last sample,
+/// FILETIME timestamps, no per-sample timestamps and no compression.
+#[derive(Debug, Clone)]
+pub struct DataStreamWriter {
+    layout: Uuid,
+    sample_data_len: usize,
+    fields: Vec<Field>,
+    types: Arc<TypeIndex>,
+    code_page: CodePage,
+    version: Version,
+    cycle_time: u32,
+    sample_timestamps: bool,
+    dc_time: bool,
+    compression: Compression,
+}
+
+impl DataStreamWriter {
+    pub fn new(symbol_stream: &SymbolStream) -> DataStreamWriter {
+        let types = TypeIndex::new(&symbol_stream.data_types);
+        let fields: Vec<_> = symbol_stream
+            .symbols
+            .iter()
+            .map(|symbol| Field::new(symbol, &types))
+            .collect();
+
+        DataStreamWriter {
+            layout: symbol_stream.header.layout,
+            sample_data_len: fields.iter().map(Field::end).max().unwrap_or(0),
+            fields,
+            types: Arc::new(types),
+            code_page: symbol_stream.code_page(),
+            version: Version { major: 1, minor: 1 },
+            cycle_time: 0,
+            sample_timestamps: false,
+            dc_time: false,
+            compression: Compression::None,
+        }
+    }
+
+    /// Header version to write, 1.0 or 1.1. Only 1.1 headers carry the sample count and the
+    /// start and stop times.
+    pub fn with_version(mut self, version: Version) -> Self {
+        self.version = version;
+        self
+    }
+
+    /// Time between two samples, in 100 ns units.
+    pub fn with_cycle_time(mut self, cycle_time: u32) -> Self {
+        self.cycle_time = cycle_time;
+        self
+    }
+
+    /// Whether to put the timestamp of every sample in front of it.
+    pub fn with_sample_timestamps(mut self, sample_timestamps: bool) -> Self {
+        self.sample_timestamps = sample_timestamps;
+        self
+    }
+
+    /// Whether to write times in EtherCAT distributed clock units rather than FILETIME.
+    pub fn with_dc_time(mut self, dc_time: bool) -> Self {
+        self.dc_time = dc_time;
+        self
+    }
+
+    pub fn with_compression(mut self, compression: Compression) -> Self {
+        self.compression = compression;
+        self
+    }
+
+    /// The layout GUID of the symbol stream this writer was built from.
+    pub fn layout(&self) -> Uuid {
+        self.layout
+    }
+
+    /// Size of the data portion of each sample: up to the end of the last symbol.
+    pub fn sample_data_len(&self) -> usize {
+        self.sample_data_len
+    }
+
+    /// Encodes `samples` as one data stream message.
+    ///
+    /// Values are matched to symbols by name. A symbol without a value of its own takes the
+    /// matching member of a value for an enclosing struct, as `Main.r_structValue.c_intMember`
+    /// does from a value for `Main.r_structValue`, so that flattened member symbols agree with
+    /// their struct. Symbols that remain without a value are written as zeros. STRING values are
+    /// encoded in the code page of the symbol stream and must not contain characters it cannot
+    /// represent.
+    pub fn write(&self, samples: &[Sample]) -> Result<Vec<u8>, EncodeError> {
+        let Version { major, minor } = self.version;
+        let header_len = match (major, minor) {
+            (1, 0) => 32,
+            (1, 1) => 56,
+            _ => return Err(EncodeError::UnsupportedVersion { major, minor }),
+        };
+        let sample_data_len =
+            u32::try_from(self.sample_data_len).map_err(|_| EncodeError::SampleTooLong {
+                len: self.sample_data_len,
+            })?;
+
+        let mut payload = Writer::new();
+        let mut data = vec![0; self.sample_data_len];
+        for (i, sample) in samples.iter().enumerate() {
+            if self.sample_timestamps {
+                let timestamp = sample
+                    .timestamp
+                    .ok_or(EncodeError::MissingTimestamp { sample: i })?;
+                payload.put_u64_le(self.raw_time(timestamp)?);
+            }
+
+            data.fill(0);
+            self.encode_sample(sample, &mut data)?;
+            payload.put_slice(&data);
+        }
+
+        let mut flags = DataStreamFlags::from((self.compression as u32) << 4);
+        flags.set(DataStreamFlags::SAMPLE_TIMESTAMP, self.sample_timestamps);
+        flags.set(DataStreamFlags::DC_TIME, self.dc_time);
+
+        let head_times = match (samples.first(), samples.last()) {
+            (Some(first), Some(last)) if minor >= 1 => first.timestamp.zip(last.timestamp),
+            _ => None,
+        };
+        flags.set(DataStreamFlags::HEAD_TIMESTAMP, head_times.is_some());
+
+        let mut stream = Writer::new();
+        stream.put_u8(major);
+        stream.put_u8(minor);
+        stream.put_u8(header_len);
+        stream.put_u8(if self.sample_timestamps { 8 } else { 0 });
+        stream.put_u32_le(sample_data_len);
+        stream.put_u32_le(self.cycle_time);
+        stream.put_u32_le(flags.bits());
+        stream.put_guid(self.layout);
+
+        if minor >= 1 {
+            let (start, stop) = match head_times {
+                Some((start, stop)) => (self.raw_time(start)?, self.raw_time(stop)?),
+                None => (0, 0),
+            };
+            stream.put_u64_le(samples.len() as u64);
+            stream.put_u64_le(start);
+            stream.put_u64_le(stop);
+        }
+
+        stream.put_slice(&self.compression.compress(&payload.into_inner()));
+        Ok(stream.into_inner())
+    }
+
+    fn encode_sample(&self, sample: &Sample, data: &mut [u8]) -> Result<(), EncodeError> {
+        for (i, field) in self.fields.iter().enumerate() {
+            // Samples produced by a decoder for the same layout hold the values in field order
+            let value = sample
+                .values
+                .get(i)
+                .filter(|value| value.name == field.name)
+                .map(|value| &value.value)
+                .or_else(|| sample.get(&field.name))
+                .or_else(|| enclosing_member(sample, &field.name));
+            let Some(value) = value else {
+                continue;
+            };
+
+            field
+                .encode(value, data, &self.types, self.code_page)
+                .ok_or_else(|| EncodeError::InvalidValue {
+                    symbol: field.name.to_string(),
+                    value: value.to_string(),
+                })?;
+        }
+        Ok(())
+    }
+
+    /// Converts `timestamp` to the time unit of the stream.
+    fn raw_time(&self, timestamp: Timestamp) -> Result<u64, EncodeError> {
+        let converted = if self.dc_time {
+            timestamp.to_dc_time()
+        } else {
+            timestamp.to_file_time()
+        };
+        converted
+            .map(Timestamp::raw)
+            .ok_or(EncodeError::TimestampOutOfRange { timestamp })
+    }
+}
+
+/// The value `sample` gives the symbol `name` through the closest enclosing struct that has a
+/// value, following the member names after it.
+fn enclosing_member<'a>(sample: &'a Sample, name: &str) -> Option<&'a PlcValue> {
+    let mut end = name.len();
+    while let Some(dot) = name[..end].rfind('.') {
+        if let Some(value) = sample.get(&name[..dot]) {
+            return name[dot + 1..]
+                .split('.')
+                .try_fold(value, |value, member| value.member(member));
+        }
+        end = dot;
+    }
+    None
+}
@@ -0,0 +1,162 @@
+use std::error::Error;
+use std::fmt;
+
+use uuid::Uuid;
+
+use crate::timestamp::Timestamp;
+
+/// Errors raised while decoding TwinCAT Analytics binary data.
+///
+/// Every variant that originates from the input carries the byte offset, relative to the start
+/// of the buffer handed to the decoder, at which decoding failed.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DecodeError {
+    /// The input ended before a field could be read.
+    Truncated { offset: usize, needed: usize },
+    /// A data type identifier that is not a known [`AdsDataType`](crate::AdsDataType).
+    UnknownAdsDataType { offset: usize, value: u32 },
+    /// A string was not NUL terminated where expected or was not valid text.
+    InvalidString { offset: usize },
+    /// A length field disagrees with the data it describes.
+    LengthMismatch {
+        offset: usize,
+        expected: usize,
+        actual: usize,
+    },
+    /// The stream uses a format version this decoder does not understand.
+    UnsupportedVersion { major: u8, minor: u8 },
+    /// A data stream was handed to a decoder built for a different symbol layout.
+    LayoutMismatch { expected: Uuid, actual: Uuid },
+    /// A data stream is compressed with a method this decoder does not understand.
+    UnsupportedCompression { method: u8 },
+    /// Text is stored in a code page this decoder cannot convert, see
+    /// [`TextMode::Strict`](crate::TextMode::Strict).
+    UnsupportedCodePage { code_page: u32 },
+    /// Data types nest deeper than a decoder follows, as a type that contains itself does.
+    NestingTooDeep { offset: usize },
+    /// The dimensions of an array hold more elements than can be counted, or than the size of
+    /// the array leaves room for.
+    InvalidArrayBounds { offset: usize },
+}
+
+impl DecodeError {
+    /// Byte offset at which decoding failed, if the error relates to a position in the input.
+    pub fn offset(&self) -> Option<usize> {
+        match *self {
+            DecodeError::Truncated { offset, .. }
+            | DecodeError::UnknownAdsDataType { offset, .. }
+            | DecodeError::InvalidString { offset }
+            | DecodeError::LengthMismatch { offset, .. }
+            | DecodeError::NestingTooDeep { offset }
+            | DecodeError::InvalidArrayBounds { offset } => Some(offset),
+            DecodeError::UnsupportedVersion { .. }
+            | DecodeError::LayoutMismatch { .. }
+            | DecodeError::UnsupportedCompression { .. }
+            | DecodeError::UnsupportedCodePage { .. } => None,
+        }
+    }
+
+    /// Shifts the reported offset by `base`, for errors raised while decoding a slice that
+    /// starts `base` bytes into the input.
+    pub(crate) fn at_offset(mut self, base: usize) -> Self {
+        match &mut self {
+            DecodeError::Truncated { offset, .. }
+            | DecodeError::UnknownAdsDataType { offset, .. }
+            | DecodeError::InvalidString { offset }
+            | DecodeError::LengthMismatch { offset, .. }
+            | DecodeError::NestingTooDeep { offset }
+            | DecodeError::InvalidArrayBounds { offset } => *offset += base,
+            DecodeError::UnsupportedVersion { .. }
+            | DecodeError::LayoutMismatch { .. }
+            | DecodeError::UnsupportedCompression { .. }
+            | DecodeError::UnsupportedCodePage { .. } => {}
+        }
+        self
+    }
+}
+
+impl fmt::Display for DecodeError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            DecodeError::Truncated { offset, needed } => {
+                write!(
+                    f,
+                    "truncated input at offset {offset}: {needed} more bytes needed"
+                )
+            }
+            DecodeError::UnknownAdsDataType { offset, value } => {
+                write!(f, "unknown ADS data type {value} at offset {offset}")
+            }
+            DecodeError::InvalidString { offset } => {
+                write!(f, "invalid string at offset {offset}")
+            }
+            DecodeError::LengthMismatch {
+                offset,
+                expected,
+                actual,
+            } => write!(
+                f,
+                "length mismatch at offset {offset}: expected {expected} bytes, found {actual}"
+            ),
+            DecodeError::UnsupportedVersion { major, minor } => {
+                write!(f, "unsupported version {major}.{minor}")
+            }
+            DecodeError::LayoutMismatch { expected, actual } => {
+                write!(f, "layout mismatch: expected {expected}, found {actual}")
+            }
+            DecodeError::UnsupportedCompression { method } => {
+                write!(f, "unsupported compression method {method}")
+            }
+            DecodeError::UnsupportedCodePage { code_page } => {
+                write!(f, "unsupported code page {code_page}")
+            }
+            DecodeError::NestingTooDeep { offset } => {
+                write!(f, "data types nested too deeply at offset {offset}")
+            }
+            DecodeError::InvalidArrayBounds { offset } => {
+                write!(f, "invalid array bounds at offset {offset}")
+            }
+        }
+    }
+}
+
+impl Error for DecodeError {}
+
+/// Errors raised while encoding TwinCAT Analytics binary data.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum EncodeError {
+    /// A value cannot be represented as the type of the symbol it is written to.
+    InvalidValue { symbol: String, value: String },
+    /// A sample has no timestamp although the stream carries one per sample.
+    MissingTimestamp { sample: usize },
+    /// A timestamp cannot be represented in the time unit of the stream.
+    TimestampOutOfRange { timestamp: Timestamp },
+    /// The requested format version cannot be written.
+    UnsupportedVersion { major: u8, minor: u8 },
+    /// Samples are longer than the 32 bit length field of the header describes.
+    SampleTooLong { len: usize },
+}
+
+impl fmt::Display for EncodeError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            EncodeError::InvalidValue { symbol, value } => {
+                write!(f, "value {value} cannot be written to {symbol}")
+            }
+            EncodeError::MissingTimestamp { sample } => {
+                write!(f, "sample {sample} has no timestamp")
+            }
+            EncodeError::TimestampOutOfRange { timestamp } => {
+                write!(f, "timestamp {timestamp} is out of range")
+            }
+            EncodeError::UnsupportedVersion { major, minor } => {
+                write!(f, "unsupported version {major}.{minor}")
+            }
+            EncodeError::SampleTooLong { len } => {
+                write!(f, "samples of {len} bytes are too long for a data stream")
+            }
+        }
+    }
+}
+
+impl Error for EncodeError {}
@@ -0,0 +1,33 @@
+//! Decoders for the binary formats published by TwinCAT Analytics.
+
+mod ads;
+mod compression;
+mod data_stream;
+mod data_stream_writer;
+mod error;
+mod reader;
+mod sample;
+mod symbol_stream;
+mod symbol_stream_ref;
+mod text;
+mod timestamp;
+mod value;
+mod writer;
+
+pub use ads::AdsDataType;
+pub use compression::Compression;
+pub use data_stream::{DataStream, DataStreamFlags, DataStreamHeader, RawSample};
+pub use data_stream_writer::DataStreamWriter;
+pub use error::{DecodeError, EncodeError};
+pub use sample::{NamedValue, Sample, SampleDecoder};
+pub use symbol_stream::{
+    ArrayDimension, Attribute, Attributes, CopyMask, DataType, DataTypeFlags, EnumItem, Method,
+    MethodParameter, MethodParameterFlags, StreamFlags, Symbol, SymbolFlags, SymbolStream,
+    SymbolStreamHeader, Version,
+};
+pub use symbol_stream_ref::{
+    AttributeRef, AttributesRef, DataTypeRef, EnumItemRef, Records, SymbolRef, SymbolStreamRef,
+};
+pub use text::{CodePage, TextMode};
+pub use timestamp::Timestamp;
+pub use value::PlcValue;
@@ -0,0 +1,131 @@
+use std::borrow::Cow;
+
+use bytes::Buf;
+use uuid::Uuid;
+
+use crate::error::DecodeError;
+use crate::text::Text;
+
+/// Bounds checked little endian cursor over a byte slice.
+///
+/// Tracks its absolute position so that errors can report where in the original input decoding
+/// failed, even when reading from a sub-slice of it. Strings are decoded as `text`, which
+/// sub-readers inherit.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct Reader<'a> {
+    buf: &'a [u8],
+    offset: usize,
+    text: Text,
+}
+
+impl<'a> Reader<'a> {
+    pub fn new(buf: &'a [u8]) -> Self {
+        Reader {
+            buf,
+            offset: 0,
+            text: Text::default(),
+        }
+    }
+
+    pub fn set_text(&mut self, text: Text) {
+        self.text = text;
+    }
+
+    /// Absolute offset of the next byte to be read.
+    pub fn offset(&self) -> usize {
+        self.offset
+    }
+
+    /// The unread part of the input.
+    pub fn rest(&self) -> &'a [u8] {
+        self.buf
+    }
+
+    fn ensure(&self, needed: usize) -> Result<(), DecodeError> {
+        if self.buf.remaining() < needed {
+            Err(DecodeError::Truncated {
+                offset: self.offset,
+                needed: needed - self.buf.remaining(),
+            })
+        } else {
+            Ok(())
+        }
+    }
+
+    pub fn advance(&mut self, cnt: usize) -> Result<(), DecodeError> {
+        self.ensure(cnt)?;
+        self.buf.advance(cnt);
+        self.offset += cnt;
+        Ok(())
+    }
+
+    pub fn get_u8(&mut self) -> Result<u8, DecodeError> {
+        self.ensure(1)?;
+        self.offset += 1;
+        Ok(self.buf.get_u8())
+    }
+
+    pub fn get_u16_le(&mut self) -> Result<u16, DecodeError> {
+        self.ensure(2)?;
+        self.offset += 2;
+        Ok(self.buf.get_u16_le())
+    }
+
+    pub fn get_u32_le(&mut self) -> Result<u32, DecodeError> {
+        self.ensure(4)?;
+        self.offset += 4;
+        Ok(self.buf.get_u32_le())
+    }
+
+    pub fn get_u64_le(&mut self) -> Result<u64, DecodeError> {
+        self.ensure(8)?;
+        self.offset += 8;
+        Ok(self.buf.get_u64_le())
+    }
+
+    /// Reads a GUID in its Windows memory layout: `data1` as a little endian `u32`, `data2` and
+    /// `data3` as little endian `u16`s, then the eight `data4` bytes in order.
+    pub fn get_guid(&mut self) -> Result<Uuid, DecodeError> {
+        let data1 = self.get_u32_le()?;
+        let data2 = self.get_u16_le()?;
+        let data3 = self.get_u16_le()?;
+        let data4: [u8; 8] = self.take(8)?.try_into().unwrap();
+        Ok(Uuid::from_fields(data1, data2, data3, &data4))
+    }
+
+    /// Reads a little endian `u32` without consuming it.
+    pub fn peek_u32_le(&self) -> Result<u32, DecodeError> {
+        self.clone().get_u32_le()
+    }
+
+    pub fn take(&mut self, len: usize) -> Result<&'a [u8], DecodeError> {
+        self.ensure(len)?;
+        let (head, tail) = self.buf.split_at(len);
+        self.buf = tail;
+        self.offset += len;
+        Ok(head)
+    }
+
+    /// Splits off the next `len` bytes as a reader of their own.
+    pub fn sub_reader(&mut self, len: usize) -> Result<Reader<'a>, DecodeError> {
+        let offset = self.offset;
+        let buf = self.take(len)?;
+        Ok(Reader {
+            buf,
+            offset,
+            text: self.text,
+        })
+    }
+
+    /// Reads a string of `len` bytes followed by a NUL terminator.
+    pub fn get_strz(&mut self, len: usize) -> Result<Cow<'a, str>, DecodeError> {
+        let offset = self.offset;
+        let raw = self.take(len + 1)?;
+        match raw.split_last() {
+            Some((0, raw)) if !raw.contains(&0) => {
+                self.text.decode(raw).map_err(|e| e.at_offset(offset))
+            }
+            _ => Err(DecodeError::InvalidString { offset }),
+        }
+    }
+}
@@ -0,0 +1,239 @@
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use uuid::Uuid;
+
+use crate::ads::AdsDataType;
+use crate::data_stream::{DataStream, RawSample};
+use crate::error::DecodeError;
+use crate::symbol_stream::{DataType, Symbol, SymbolFlags, SymbolStream};
+use crate::text::{CodePage, Text, TextMode};
+use crate::timestamp::Timestamp;
+use crate::value::{decode_sized, decode_type, encode_sized, encode_type, PlcValue, TypeLookup};
+
+/// Decodes the samples of data streams using the layout described by a symbol stream.
+///
+/// STRING values are decoded in the code page of the symbol stream and WSTRING values as
+/// UTF-16LE. Malformed text is replaced unless [`TextMode::Strict`] is selected with
+/// [`with_text_mode`](SampleDecoder::with_text_mode).
+#[derive(Debug, Clone)]
+pub struct SampleDecoder {
+    layout: Uuid,
+    fields: Vec<Field>,
+    types: Arc<TypeIndex>,
+    text: Text,
+}
+
+/// Where and how to find one symbol within a sample.
+#[derive(Debug, Clone)]
+pub(crate) struct Field {
+    pub name: Arc<str>,
+    data_type: AdsDataType,
+    offset: usize,
+    bit: Option<u8>,
+    len: usize,
+    /// Index of the symbol's type in the decoder's [`TypeIndex`], if it is known.
+    type_index: Option<usize>,
+}
+
+/// The data types of the layout, indexed by name for resolving compound values.
+#[derive(Debug)]
+pub(crate) struct TypeIndex {
+    types: Vec<DataType>,
+    by_name: HashMap<String, usize>,
+}
+
+/// A decoded sample: the value of every symbol of the layout, in symbol stream order.
+#[derive(Debug, Clone)]
+pub struct Sample {
+    pub timestamp: Option<Timestamp>,
+    pub values: Vec<NamedValue>,
+}
+
+#[derive(Debug, Clone)]
+pub struct NamedValue {
+    pub name: Arc<str>,
+    pub value: PlcValue,
+}
+
+impl Sample {
+    /// Value of the symbol called `name`.
+    pub fn get(&self, name: &str) -> Option<&PlcValue> {
+        self.values
+            .iter()
+            .find(|v| &*v.name == name)
+            .map(|v| &v.value)
+    }
+}
+
+impl SampleDecoder {
+    pub fn new(symbol_stream: &SymbolStream) -> SampleDecoder {
+        let types = TypeIndex::new(&symbol_stream.data_types);
+        let fields = symbol_stream
+            .symbols
+            .iter()
+            .map(|symbol| Field::new(symbol, &types))
+            .collect();
+
+        SampleDecoder {
+            layout: symbol_stream.header.layout,
+            fields,
+            types: Arc::new(types),
+            text: Text {
+                code_page: symbol_stream.code_page(),
+                mode: TextMode::Lossy,
+            },
+        }
+    }
+
+    pub fn with_text_mode(mut self, mode: TextMode) -> Self {
+        self.text.mode = mode;
+        self
+    }
+
+    /// The layout GUID of the symbol stream this decoder was built from.
+    pub fn layout(&self) -> Uuid {
+        self.layout
+    }
+
+    /// Decodes every sample of `stream`, which must use this decoder's layout.
+    pub fn decode(&self, stream: &DataStream) -> Result<Vec<Sample>, DecodeError> {
+        if stream.header.layout != self.layout {
+            return Err(DecodeError::LayoutMismatch {
+                expected: self.layout,
+                actual: stream.header.layout,
+            });
+        }
+
+        stream
+            .samples()
+            .map(|sample| self.decode_sample(&sample))
+            .collect()
+    }
+
+    /// Decodes a single sample. Error offsets are relative to the start of the sample data.
+    pub fn decode_sample(&self, sample: &RawSample) -> Result<Sample, DecodeError> {
+        let values = self
+            .fields
+            .iter()
+            .map(|field| {
+                let value = field.decode(&sample.data, &self.types, self.text)?;
+                Ok(NamedValue {
+                    name: field.name.clone(),
+                    value,
+                })
+            })
+            .collect::<Result<_, DecodeError>>()?;
+
+        Ok(Sample {
+            timestamp: sample.timestamp,
+            values,
+        })
+    }
+}
+
+impl Field {
+    pub fn new(symbol: &Symbol, types: &TypeIndex) -> Field {
+        let (offset, bit) = symbol.sample_location();
+        Field {
+            name: symbol.name.as_str().into(),
+            data_type: symbol.data_type,
+            offset,
+            bit,
+            len: symbol.len,
+            type_index: types.by_name.get(&symbol.data_type_name).copied(),
+        }
+    }
+
+    /// Offset just past the last byte of the sample the symbol occupies.
+    pub fn end(&self) -> usize {
+        self.offset + if self.bit.is_some() { 1 } else { self.len }
+    }
+
+    pub fn decode(
+        &self,
+        data: &[u8],
+        types: &TypeIndex,
+        text: Text,
+    ) -> Result<PlcValue, DecodeError> {
+        let end = self.end();
+        let bytes = data
+            .get(self.offset..end)
+            .ok_or_else(|| DecodeError::Truncated {
+                offset: data.len(),
+                needed: end - data.len(),
+            })?;
+
+        if let Some(bit) = self.bit {
+            return Ok(PlcValue::Bool(bytes[0] & (1 << bit) != 0));
+        }
+
+        match self.type_index {
+            Some(index) => decode_type(&types.types[index], bytes, types, text, 0),
+            None => decode_sized(self.data_type, bytes, text),
+        }
+        .map_err(|e| e.at_offset(self.offset))
+    }
+
+    /// Writes `value` into the sample `data`. Returns `None` if the value cannot be represented
+    /// as the symbol's type, or a string in `code_page`.
+    pub fn encode(
+        &self,
+        value: &PlcValue,
+        data: &mut [u8],
+        types: &TypeIndex,
+        code_page: CodePage,
+    ) -> Option<()> {
+        let out = data.get_mut(self.offset..self.end())?;
+
+        if let Some(bit) = self.bit {
+            match value.as_i128()? {
+                0 => out[0] &= !(1 << bit),
+                _ => out[0] |= 1 << bit,
+            }
+            return Some(());
+        }
+
+        match self.type_index {
+            Some(index) => encode_type(&types.types[index], value, out, types, code_page, 0),
+            None => encode_sized(self.data_type, value, out, code_page),
+        }
+    }
+}
+
+impl TypeIndex {
+    pub fn new(data_types: &[DataType]) -> TypeIndex {
+        let by_name = data_types
+            .iter()
+            .enumerate()
+            .map(|(i, data_type)| (data_type.name.clone(), i))
+            .collect();
+        TypeIndex {
+            types: data_types.to_vec(),
+            by_name,
+        }
+    }
+}
+
+impl TypeLookup for TypeIndex {
+    fn data_type(&self, name: &str) -> Option<&DataType> {
+        self.by_name.get(name).map(|&i| &self.types[i])
+    }
+}
+
+impl Symbol {
+    /// Location of the symbol within a data stream sample: the byte offset, and for bit valued
+    /// symbols the bit within that byte.
+    ///
+    /// In Analytics symbol streams the low 24 bits of the index offset address the symbol
+    /// relative to the start of the sample, counted in bits for bit valued symbols. The top
+    /// byte holds the memory area.
+    pub fn sample_location(&self) -> (usize, Option<u8>) {
+        let address = (self.index_offset & 0x00FF_FFFF) as usize;
+        if self.flags.contains(SymbolFlags::BIT_VALUE) {
+            (address / 8, Some((address % 8) as u8))
+        } else {
+            (address, None)
+        }
+    }
+}
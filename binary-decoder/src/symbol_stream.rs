@@ -0,0 +1,1124 @@
+use std::fmt;
+
+use bitflags::bitflags;
+use uuid::Uuid;
+
+use crate::ads::AdsDataType;
+use crate::error::DecodeError;
+use crate::reader::Reader;
+use crate::symbol_stream_ref::{
+    AttributeRef, AttributesRef, DataTypeRef, EnumItemRef, Records, SymbolRef, SymbolStreamRef,
+};
+use crate::text::{CodePage, Text, TextMode};
+use crate::value::{
+    decode_sized, decode_type, integer_value, PlcValue, TypeLookup, MAX_TYPE_DEPTH,
+};
+use crate::writer::Writer;
+
+#[derive(Debug, Clone)]
+pub struct SymbolStream {
+    pub header: SymbolStreamHeader,
+    pub symbols: Vec<Symbol>,
+    pub data_types: Vec<DataType>,
+}
+
+#[derive(Debug, Copy, Clone)]
+pub struct Version {
+    pub major: u8,
+    pub minor: u8,
+}
+
+impl fmt::Display for Version {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}.{}", self.major, self.minor)
+    }
+}
+
+#[derive(Debug, Copy, Clone)]
+pub struct SymbolStreamHeader {
+    pub version: Version,
+    pub header_len: u16,
+    pub symbol_count: u32,
+    pub symbol_data_len: usize,
+    pub data_type_count: u32,
+    pub data_type_data_len: usize,
+    pub used_dynamic_symbols: u32,
+    pub code_page: u32,
+    pub flags: StreamFlags,
+    pub layout: Uuid,
+}
+
+bitflags! {
+    /// Flags in the symbol stream header.
+    #[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+    pub struct StreamFlags: u32 {
+        const ONLINE_CHANGE = 1 << 0;
+        const TARGET_64_BIT = 1 << 1;
+        const BASE_TYPES_INCLUDED = 1 << 2;
+        const PERFORM_Q_SORT = 1 << 3;
+
+        // Keep bits we do not know about yet
+        const _ = !0;
+    }
+}
+
+impl From<u32> for StreamFlags {
+    fn from(v: u32) -> Self {
+        StreamFlags::from_bits_retain(v)
+    }
+}
+
+bitflags! {
+    /// Flags of a symbol record.
+    #[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+    pub struct SymbolFlags: u32 {
+        const PERSISTENT = 1 << 0;
+        const BIT_VALUE = 1 << 1;
+        const REFERENCE_TO = 1 << 2;
+        const TYPE_GUID = 1 << 3;
+        const TWINCAT_COM_INTERFACE_POINTER = 1 << 4;
+        const READ_ONLY = 1 << 5;
+        const INTERFACE_METHOD_ACCESS = 1 << 6;
+        const METHOD_DEREF = 1 << 7;
+        const CONTEXT_MASK = 0b1111 << 8;
+        const ATTRIBUTES = 1 << 12;
+        const STATIC = 1 << 13;
+        const INITIALISED_ON_RESET = 1 << 14;
+        const EXTENDED_FLAGS = 1 << 15;
+
+        // Keep bits we do not know about yet
+        const _ = !0;
+    }
+}
+
+impl SymbolFlags {
+    /// The four bit context (task) field.
+    pub fn context_mask(&self) -> u8 {
+        ((self.bits() & SymbolFlags::CONTEXT_MASK.bits()) >> 8) as u8
+    }
+}
+
+impl From<u32> for SymbolFlags {
+    fn from(v: u32) -> Self {
+        SymbolFlags::from_bits_retain(v)
+    }
+}
+
+bitflags! {
+    /// Flags of a data type record.
+    #[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+    pub struct DataTypeFlags: u32 {
+        const DATA_TYPE = 1 << 0;
+        const DATA_ITEM = 1 << 1;
+        const REFERENCE_TO = 1 << 2;
+        const METHOD_DEREF = 1 << 3;
+        const OVERSAMPLING_ARRAY = 1 << 4;
+        const BIT_VALUE = 1 << 5;
+        const PROPERTY_ITEM = 1 << 6;
+        const TYPE_GUID = 1 << 7;
+        const PERSISTENT = 1 << 8;
+        const COPY_MASK = 1 << 9;
+        const TWINCAT_COM_INTERFACE_POINTER = 1 << 10;
+        const METHOD_INFOS = 1 << 11;
+        const ATTRIBUTES = 1 << 12;
+        const ENUM_INFOS = 1 << 13;
+        const BYTE_ALIGNED = 1 << 16;
+        const STATIC = 1 << 17;
+        const SP_LEVELS = 1 << 18;
+        const IGNORE_PERSIST = 1 << 19;
+        const ANY_SIZE_ARRAY = 1 << 20;
+        const PERSISTENT_DATA_TYPE = 1 << 21;
+        const INITIALISED_ON_RESULT = 1 << 22;
+
+        // Keep bits we do not know about yet
+        const _ = !0;
+    }
+}
+
+impl From<u32> for DataTypeFlags {
+    fn from(v: u32) -> Self {
+        DataTypeFlags::from_bits_retain(v)
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Symbol {
+    pub index_group: u32,
+    pub index_offset: u32,
+    pub len: usize,
+    pub data_type: AdsDataType,
+    pub flags: SymbolFlags,
+    pub name: String,
+    pub data_type_name: String,
+    pub comment: String,
+    pub data_type_guid: Option<Uuid>,
+    pub attributes: Attributes,
+}
+
+#[derive(Debug, Clone)]
+pub struct DataType {
+    pub version: u32,
+    
+    pub hash_value: u32, //Don't know what this is
+    pub type_hash_value: u32, // Don't know what this is
+    
+    pub data_type_len: u32,
+    
+    /// Position of a sub item within its parent, in bytes, or in bits for bit valued members.
+    pub offset: u32,
+    
+    pub base_data_type: AdsDataType,
+    
+    pub flags: DataTypeFlags,
+    
+    pub array_dimension_count: u16,
+    pub sub_item_count: u16,
+    
+    pub name: String,
+    pub data_type_name: String,
+    pub comment: String,
+    
+    pub array_dimensions: Vec<ArrayDimension>,
+    /// Members of a struct or function block, each located at its own `offset`.
+    pub sub_items: Vec<DataType>,
+
+    pub guid: Option<Uuid>,
+    pub copy_mask: Option<CopyMask>,
+    
+    pub methods: Vec<Method>,
+    pub attributes: Attributes,
+    
+    pub enum_items: Vec<EnumItem>,
+}
+
+impl DataType {
+    pub fn is_array(&self) -> bool {
+        !self.array_dimensions.is_empty()
+    }
+
+    pub fn is_struct(&self) -> bool {
+        !self.sub_items.is_empty()
+    }
+
+    pub fn is_enum(&self) -> bool {
+        !self.enum_items.is_empty()
+    }
+
+    /// Name of the enumerator with the given raw value, e.g. `Running` for `3`.
+    pub fn enum_name(&self, value: i64) -> Option<&str> {
+        self.enum_items
+            .iter()
+            .find(|item| item.value == value)
+            .map(|item| item.name.as_str())
+    }
+
+    /// Raw value of the enumerator called `name`.
+    pub fn enum_value(&self, name: &str) -> Option<i64> {
+        self.enum_items
+            .iter()
+            .find(|item| item.name == name)
+            .map(|item| item.value)
+    }
+
+    /// Total number of elements across all array dimensions, or `1` for non-array types. `None`
+    /// if the count does not fit in a `usize`.
+    pub fn element_count(&self) -> Option<usize> {
+        element_count(&self.array_dimensions)
+    }
+
+    /// Size in bytes of a single array element. `None` if the element count does not fit in a
+    /// `usize`.
+    pub fn element_stride(&self) -> Option<usize> {
+        match self.element_count()? {
+            0 => Some(0),
+            n => Some(self.data_type_len as usize / n),
+        }
+    }
+
+    /// Converts a multi-dimensional index, using the declared bounds, into the position of the
+    /// element in memory. The last dimension varies fastest.
+    pub fn flat_index(&self, index: &[i32]) -> Option<usize> {
+        if index.len() != self.array_dimensions.len() {
+            return None;
+        }
+
+        let mut flat: usize = 0;
+        for (dimension, &i) in self.array_dimensions.iter().zip(index) {
+            flat = flat
+                .checked_mul(dimension.element_count as usize)?
+                .checked_add(dimension.position(i)?)?;
+        }
+        Some(flat)
+    }
+
+    /// Converts the position of an element in memory back into its multi-dimensional index.
+    pub fn multi_index(&self, flat: usize) -> Option<Vec<i32>> {
+        if flat >= self.element_count()? || !self.is_array() {
+            return None;
+        }
+
+        let mut remainder = flat;
+        let mut index = vec![0; self.array_dimensions.len()];
+        for (dimension, i) in self.array_dimensions.iter().zip(index.iter_mut()).rev() {
+            let count = dimension.element_count as usize;
+            // Every count is at least one here, and the position within it fits in a u32
+            let position = (remainder % count) as u32;
+            *i = i32::try_from(i64::from(dimension.lower_bound) + i64::from(position)).ok()?;
+            remainder /= count;
+        }
+        Some(index)
+    }
+
+    /// Looks up a direct member of a struct or function block by name.
+    pub fn sub_item(&self, name: &str) -> Option<&DataType> {
+        self.sub_items.iter().find(|item| item.name == name)
+    }
+}
+
+/// Marks which bits of a value of a data type carry data, as opposed to alignment padding.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CopyMask(pub Vec<u8>);
+
+impl CopyMask {
+    /// Whether any bit of the byte at `index` is covered by the mask.
+    pub fn is_significant(&self, index: usize) -> bool {
+        self.0.get(index).is_some_and(|&m| m != 0)
+    }
+
+    /// Masks `value`, zeroing every bit that is not significant. Bytes beyond the end of the
+    /// mask are dropped.
+    pub fn apply(&self, value: &[u8]) -> Vec<u8> {
+        value.iter().zip(&self.0).map(|(v, m)| v & m).collect()
+    }
+
+    /// Positions and masked contents of the significant bytes of `value`.
+    pub fn significant_bytes<'a>(
+        &'a self,
+        value: &'a [u8],
+    ) -> impl Iterator<Item = (usize, u8)> + 'a {
+        value
+            .iter()
+            .zip(&self.0)
+            .enumerate()
+            .filter(|(_, (_, &m))| m != 0)
+            .map(|(i, (v, m))| (i, v & m))
+    }
+
+    /// Whether two raw values differ in any significant bit.
+    pub fn differs(&self, a: &[u8], b: &[u8]) -> bool {
+        self.apply(a) != self.apply(b)
+    }
+}
+
+/// A method of a function block or interface.
+#[derive(Debug, Clone)]
+pub struct Method {
+    pub version: u32,
+    pub vtable_index: u32,
+    pub return_size: u32,
+    pub return_align_size: u32,
+    pub reserved: u32,
+    pub return_type_guid: Uuid,
+    pub return_data_type: AdsDataType,
+    pub flags: u32,
+    pub name: String,
+    pub return_type: String,
+    pub comment: String,
+    pub parameters: Vec<MethodParameter>,
+}
+
+#[derive(Debug, Clone)]
+pub struct MethodParameter {
+    pub size: u32,
+    pub align_size: u32,
+    pub data_type: AdsDataType,
+    pub flags: MethodParameterFlags,
+    pub reserved: u32,
+    pub type_guid: Uuid,
+    /// Index of the parameter that carries the length of this one, for dynamic arrays.
+    pub length_is_parameter: u16,
+    pub name: String,
+    pub type_name: String,
+    pub comment: String,
+}
+
+bitflags! {
+    /// Direction and passing of a method parameter.
+    #[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+    pub struct MethodParameterFlags: u32 {
+        const IN = 1 << 0;
+        const OUT = 1 << 1;
+        const REFERENCE = 1 << 2;
+
+        // Keep bits we do not know about yet
+        const _ = !0;
+    }
+}
+
+impl From<u32> for MethodParameterFlags {
+    fn from(v: u32) -> Self {
+        MethodParameterFlags::from_bits_retain(v)
+    }
+}
+
+/// A single `{attribute 'key' := 'value'}` pragma.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Attribute {
+    pub key: String,
+    pub value: String,
+}
+
+/// The attribute pragmas attached to a symbol or data type, in declaration order.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Attributes(pub Vec<Attribute>);
+
+impl Attributes {
+    /// Value of the attribute named `key`. Attribute names are matched case-insensitively, as
+    /// they are by the TwinCAT compiler.
+    pub fn get(&self, key: &str) -> Option<&str> {
+        self.0
+            .iter()
+            .find(|a| a.key.eq_ignore_ascii_case(key))
+            .map(|a| a.value.as_str())
+    }
+
+    pub fn contains(&self, key: &str) -> bool {
+        self.get(key).is_some()
+    }
+
+    pub fn iter(&self) -> std::slice::Iter<'_, Attribute> {
+        self.0.iter()
+    }
+
+    pub fn len(&self) -> usize {
+        self.0.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+}
+
+impl<'a> IntoIterator for &'a Attributes {
+    type Item = &'a Attribute;
+    type IntoIter = std::slice::Iter<'a, Attribute>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+/// One enumerator of an ENUM data type.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EnumItem {
+    pub name: String,
+    /// Value of the enumerator, sign extended when the underlying base type is signed.
+    pub value: i64,
+}
+
+/// Bounds of one dimension of an array, e.g. `1..4` is stored as a lower bound of `1` and four
+/// elements.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct ArrayDimension {
+    pub lower_bound: i32,
+    pub element_count: u32,
+}
+
+impl ArrayDimension {
+    /// Inclusive upper bound as written in the declaration. `None` if it is out of range for a
+    /// DINT, which no valid declaration is.
+    pub fn upper_bound(&self) -> Option<i32> {
+        let upper = i64::from(self.lower_bound) + i64::from(self.element_count) - 1;
+        i32::try_from(upper).ok()
+    }
+
+    /// Zero based position of `index` within this dimension.
+    fn position(&self, index: i32) -> Option<usize> {
+        let position = usize::try_from(i64::from(index) - i64::from(self.lower_bound)).ok()?;
+        (position < self.element_count as usize).then_some(position)
+    }
+}
+
+/// Total number of elements across `dimensions`, `None` if it does not fit in a `usize`.
+pub(crate) fn element_count(dimensions: &[ArrayDimension]) -> Option<usize> {
+    dimensions
+        .iter()
+        .try_fold(1usize, |count, d| count.checked_mul(d.element_count as usize))
+}
+
+impl SymbolStream {
+    /// Decodes a complete symbol stream, as published on the `Bin/Tx/Symbols` topic.
+    ///
+    /// Names and comments are decoded in the code page given in the header and must be valid
+    /// text in it, see [`parse_with`](SymbolStream::parse_with).
+    pub fn parse(data: &[u8]) -> Result<SymbolStream, DecodeError> {
+        SymbolStream::parse_with(data, TextMode::Strict)
+    }
+
+    /// Decodes a complete symbol stream, handling text that is not valid in the header's code
+    /// page as `mode` says.
+    pub fn parse_with(data: &[u8], mode: TextMode) -> Result<SymbolStream, DecodeError> {
+        SymbolStreamRef::parse_with(data, mode)?.into_owned()
+    }
+
+    /// The code page of names, comments and STRING values.
+    pub fn code_page(&self) -> CodePage {
+        CodePage(self.header.code_page)
+    }
+
+    /// Encodes the symbol stream in the format [`parse`](SymbolStream::parse) reads.
+    ///
+    /// Counts and lengths are derived from the symbols and data types rather than taken from
+    /// the header. Text is encoded in the header's code page, writing characters it cannot
+    /// represent as `?`. Top level records are padded to a multiple of eight bytes and reserved
+    /// fields are written as zeros, as TwinCAT does, so a parsed stream encodes back to the
+    /// same bytes.
+    ///
+    /// # Panics
+    ///
+    /// Panics if a name, comment or list is too long for its length field.
+    pub fn encode(&self) -> Vec<u8> {
+        let mut stream = Writer::new();
+        encode(self, &mut stream);
+        stream.into_inner()
+    }
+
+    /// The data type called `name`.
+    pub fn data_type(&self, name: &str) -> Option<&DataType> {
+        self.data_types.data_type(name)
+    }
+
+    /// Decodes the value of `symbol` from the start of `bytes`, resolving its data type and the
+    /// types of its members and elements in this stream's data type table. Structs, arrays and
+    /// enumerations decode to nested values; types that are not in the table are decoded from
+    /// the symbol's base type. STRING values are decoded in the stream's code page, replacing
+    /// malformed text.
+    pub fn decode_symbol(&self, symbol: &Symbol, bytes: &[u8]) -> Result<PlcValue, DecodeError> {
+        let text = Text {
+            code_page: self.code_page(),
+            mode: TextMode::Lossy,
+        };
+        match self.data_type(&symbol.data_type_name) {
+            Some(data_type) => decode_type(data_type, bytes, &self.data_types[..], text, 0),
+            None => {
+                let bytes = bytes.get(..symbol.len).ok_or_else(|| DecodeError::Truncated {
+                    offset: bytes.len(),
+                    needed: symbol.len - bytes.len(),
+                })?;
+                decode_sized(symbol.data_type, bytes, text)
+            }
+        }
+    }
+}
+
+pub(crate) fn parse<'a>(
+    stream: &mut Reader<'a>,
+    mode: TextMode,
+) -> Result<SymbolStreamRef<'a>, DecodeError> {
+    let header = parse_header(stream)?;
+
+    // All text after the header is in the code page it names
+    let code_page = CodePage(header.code_page);
+    stream.set_text(Text { code_page, mode });
+
+    // Symbols and data types are only decoded when they are iterated over
+    let symbol_data = stream.sub_reader(header.symbol_data_len)?;
+    let data_type_data = stream.sub_reader(header.data_type_data_len)?;
+
+    Ok(SymbolStreamRef {
+        header,
+        symbol_data,
+        data_type_data,
+    })
+}
+
+/// Newest major version of the symbol stream format understood, the one TwinCAT 3 publishes.
+/// A newer major version may lay out its records differently.
+const MAX_MAJOR_VERSION: u8 = 3;
+
+fn parse_header(stream: &mut Reader) -> Result<SymbolStreamHeader, DecodeError> {
+    let start = stream.offset();
+
+    let major = stream.get_u8()?;
+    let minor = stream.get_u8()?;
+    let version = Version { major, minor };
+    // Minor revisions only append to the header, which is skipped below
+    if major > MAX_MAJOR_VERSION {
+        return Err(DecodeError::UnsupportedVersion { major, minor });
+    }
+
+    let header_len = stream.get_u16_le()?;
+    let symbol_count = stream.get_u32_le()?;
+    let symbol_data_len = stream.get_u32_le()? as usize;
+    let data_type_count = stream.get_u32_le()?;
+    let data_type_data_len = stream.get_u32_le()? as usize;
+    let used_dynamic_symbols = stream.get_u32_le()?;
+    let code_page = stream.get_u32_le()?;
+
+    let flags = stream.get_u32_le()?;
+    let flags = StreamFlags::from(flags);
+
+    // Reserved
+    stream.advance(16)?;
+
+    let layout = stream.get_guid()?;
+
+    // Skip anything a newer header revision may have appended
+    let consumed = stream.offset() - start;
+    let padding = (header_len as usize)
+        .checked_sub(consumed)
+        .ok_or(DecodeError::LengthMismatch {
+            offset: start + 2,
+            expected: consumed,
+            actual: header_len as usize,
+        })?;
+    stream.advance(padding)?;
+
+    Ok(SymbolStreamHeader {
+        version,
+        header_len,
+        symbol_count,
+        symbol_data_len,
+        data_type_count,
+        data_type_data_len,
+        used_dynamic_symbols,
+        code_page,
+        flags,
+        layout,
+    })
+}
+
+/// Splits off the next length prefixed record. The length includes the prefix itself.
+pub(crate) fn next_record<'a>(stream: &mut Reader<'a>) -> Result<Reader<'a>, DecodeError> {
+    let offset = stream.offset();
+    let len = stream.peek_u32_le()? as usize;
+    if len < 4 {
+        return Err(DecodeError::LengthMismatch {
+            offset,
+            expected: 4,
+            actual: len,
+        });
+    }
+    stream.sub_reader(len)
+}
+
+fn parse_ads_data_type(stream: &mut Reader) -> Result<AdsDataType, DecodeError> {
+    let offset = stream.offset();
+    let value = stream.get_u32_le()?;
+    value
+        .try_into()
+        .map_err(|_| DecodeError::UnknownAdsDataType { offset, value })
+}
+
+/// Runs `parse` on a copy of `stream` and splits off the part it consumed, so that it can be
+/// decoded again later without allocating.
+fn skip<'a, F>(stream: &mut Reader<'a>, parse: F) -> Result<Reader<'a>, DecodeError>
+where
+    F: FnOnce(&mut Reader<'a>) -> Result<(), DecodeError>,
+{
+    let mut probe = stream.clone();
+    parse(&mut probe)?;
+    stream.sub_reader(probe.offset() - stream.offset())
+}
+
+/// Splits off `count` length prefixed records without decoding them. They are decoded at
+/// nesting `depth` as they are iterated over.
+fn skip_records<'a, T>(
+    count: usize,
+    depth: usize,
+    stream: &mut Reader<'a>,
+    parse: fn(&mut Reader<'a>, usize) -> Result<T, DecodeError>,
+) -> Result<Records<'a, T>, DecodeError> {
+    let records = skip(stream, |stream| {
+        for _ in 0..count {
+            next_record(stream)?;
+        }
+        Ok(())
+    })?;
+    Ok(Records::new(records, count, depth, parse))
+}
+
+pub(crate) fn parse_symbol<'a>(stream: &mut Reader<'a>) -> Result<SymbolRef<'a>, DecodeError> {
+    // Skip len since we already know that from next_record()
+    stream.advance(4)?;
+
+    let index_group = stream.get_u32_le()?;
+    let index_offset = stream.get_u32_le()?;
+
+    let data_len = stream.get_u32_le()? as usize;
+
+    let data_type = parse_ads_data_type(stream)?;
+
+    let flags = stream.get_u32_le()?;
+    let flags: SymbolFlags = flags.into();
+
+    let name_len = stream.get_u16_le()? as usize;
+    let data_type_name_len = stream.get_u16_le()? as usize;
+    let comment_len = stream.get_u16_le()? as usize;
+
+    let name = stream.get_strz(name_len)?;
+
+    let data_type_name = stream.get_strz(data_type_name_len)?;
+
+    let comment = stream.get_strz(comment_len)?;
+
+    let data_type_guid = if flags.contains(SymbolFlags::TYPE_GUID) {
+        Some(stream.get_guid()?)
+    } else { None };
+
+    let attributes = if flags.contains(SymbolFlags::ATTRIBUTES) {
+        parse_attributes(stream)?
+    } else { AttributesRef::default() };
+
+    Ok(SymbolRef {
+        index_group,
+        index_offset,
+        len: data_len,
+        data_type,
+        flags,
+        name,
+        data_type_name,
+        comment,
+        data_type_guid,
+        attributes,
+    })
+}
+
+/// Decodes a data type record nested `depth` records deep. Sub items are records within the
+/// record, which a crafted stream can nest until converting them recursively overflows the
+/// stack, so they are only followed as deep as values are decoded.
+pub(crate) fn parse_data_type<'a>(
+    stream: &mut Reader<'a>,
+    depth: usize,
+) -> Result<DataTypeRef<'a>, DecodeError> {
+    if depth > MAX_TYPE_DEPTH {
+        return Err(DecodeError::NestingTooDeep { offset: stream.offset() });
+    }
+
+    // Skip len since we already know that from next_record()
+    stream.advance(4)?;
+
+    let version = stream.get_u32_le()?;
+
+    let hash_value = stream.get_u32_le()?;
+    let type_hash_value = stream.get_u32_le()?;
+
+    let data_type_len = stream.get_u32_le()?;
+    let offset = stream.get_u32_le()?;
+
+    let base_data_type = parse_ads_data_type(stream)?;
+
+    let flags = stream.get_u32_le()?;
+    let flags: DataTypeFlags = flags.into();
+
+    let name_len = stream.get_u16_le()? as usize;
+    let data_type_name_len = stream.get_u16_le()? as usize;
+    let comment_len = stream.get_u16_le()? as usize;
+
+    let array_dimension_count = stream.get_u16_le()?;
+    let sub_item_count = stream.get_u16_le()?;
+
+    let name = stream.get_strz(name_len)?;
+
+    let data_type_name = stream.get_strz(data_type_name_len)?;
+
+    let comment = stream.get_strz(comment_len)?;
+
+    // Pairs of lower bound and element count
+    let array_dimensions = stream.take(8 * array_dimension_count as usize)?;
+
+    // Sub items are nested data type records, each with its own length prefix
+    let sub_items = skip_records(sub_item_count.into(), depth + 1, stream, parse_data_type)?;
+
+    let guid = if flags.contains(DataTypeFlags::TYPE_GUID) {
+        Some(stream.get_guid()?)
+    } else { None };
+
+    let copy_mask = if flags.contains(DataTypeFlags::COPY_MASK) {
+        Some(stream.take(data_type_len as usize)?)
+    } else { None };
+
+    let methods = if flags.contains(DataTypeFlags::METHOD_INFOS) {
+        let count = stream.get_u16_le()?;
+        skip_records(count.into(), 0, stream, |stream, _| parse_method(stream))?
+    } else { Records::new(Reader::default(), 0, 0, |stream, _| parse_method(stream)) };
+
+    let attributes = if flags.contains(DataTypeFlags::ATTRIBUTES) {
+        parse_attributes(stream)?
+    } else { AttributesRef::default() };
+
+    let (enum_items, enum_item_count) = if flags.contains(DataTypeFlags::ENUM_INFOS) {
+        parse_enum_items(data_type_len as usize, base_data_type, stream)?
+    } else { (Reader::default(), 0) };
+
+    Ok(DataTypeRef {
+        version,
+        hash_value,
+        type_hash_value,
+        data_type_len,
+        offset,
+        base_data_type,
+        flags,
+        array_dimension_count,
+        sub_item_count,
+        name,
+        data_type_name,
+        comment,
+        array_dimensions,
+        sub_items,
+        guid,
+        copy_mask,
+        methods,
+        attributes,
+        enum_items,
+        enum_item_count,
+    })
+}
+
+fn parse_method(stream: &mut Reader) -> Result<Method, DecodeError> {
+    // Skip len since we already know that from next_record()
+    stream.advance(4)?;
+
+    let version = stream.get_u32_le()?;
+    let vtable_index = stream.get_u32_le()?;
+    let return_size = stream.get_u32_le()?;
+    let return_align_size = stream.get_u32_le()?;
+    let reserved = stream.get_u32_le()?;
+    let return_type_guid = stream.get_guid()?;
+    let return_data_type = parse_ads_data_type(stream)?;
+    let flags = stream.get_u32_le()?;
+
+    let name_len = stream.get_u16_le()? as usize;
+    let return_type_len = stream.get_u16_le()? as usize;
+    let comment_len = stream.get_u16_le()? as usize;
+    let parameter_count = stream.get_u16_le()?;
+
+    let name = stream.get_strz(name_len)?.into_owned();
+    let return_type = stream.get_strz(return_type_len)?.into_owned();
+    let comment = stream.get_strz(comment_len)?.into_owned();
+
+    let mut parameters = Vec::with_capacity(parameter_count.into());
+    for _ in 0..parameter_count {
+        let mut parameter_data = next_record(stream)?;
+        parameters.push(parse_method_parameter(&mut parameter_data)?);
+    }
+
+    Ok(Method {
+        version,
+        vtable_index,
+        return_size,
+        return_align_size,
+        reserved,
+        return_type_guid,
+        return_data_type,
+        flags,
+        name,
+        return_type,
+        comment,
+        parameters,
+    })
+}
+
+fn parse_method_parameter(stream: &mut Reader) -> Result<MethodParameter, DecodeError> {
+    // Skip len since we already know that from parse_method()
+    stream.advance(4)?;
+
+    let size = stream.get_u32_le()?;
+    let align_size = stream.get_u32_le()?;
+    let data_type = parse_ads_data_type(stream)?;
+    let flags = stream.get_u32_le()?.into();
+    let reserved = stream.get_u32_le()?;
+    let type_guid = stream.get_guid()?;
+    let length_is_parameter = stream.get_u16_le()?;
+
+    let name_len = stream.get_u16_le()? as usize;
+    let type_name_len = stream.get_u16_le()? as usize;
+    let comment_len = stream.get_u16_le()? as usize;
+
+    let name = stream.get_strz(name_len)?.into_owned();
+    let type_name = stream.get_strz(type_name_len)?.into_owned();
+    let comment = stream.get_strz(comment_len)?.into_owned();
+
+    Ok(MethodParameter {
+        size,
+        align_size,
+        data_type,
+        flags,
+        reserved,
+        type_guid,
+        length_is_parameter,
+        name,
+        type_name,
+        comment,
+    })
+}
+
+fn parse_attributes<'a>(stream: &mut Reader<'a>) -> Result<AttributesRef<'a>, DecodeError> {
+    let count = stream.get_u16_le()?;
+    let data = skip(stream, |stream| {
+        for _ in 0..count {
+            parse_attribute(stream)?;
+        }
+        Ok(())
+    })?;
+    Ok(AttributesRef { data, count })
+}
+
+pub(crate) fn parse_attribute<'a>(stream: &mut Reader<'a>) -> Result<AttributeRef<'a>, DecodeError> {
+    let key_len = stream.get_u8()? as usize;
+    let value_len = stream.get_u8()? as usize;
+    let key = stream.get_strz(key_len)?;
+    let value = stream.get_strz(value_len)?;
+    Ok(AttributeRef { key, value })
+}
+
+fn parse_enum_items<'a>(
+    len: usize,
+    base_data_type: AdsDataType,
+    stream: &mut Reader<'a>,
+) -> Result<(Reader<'a>, u16), DecodeError> {
+    let count = stream.get_u16_le()?;
+    let items = skip(stream, |stream| {
+        for _ in 0..count {
+            parse_enum_item(len, base_data_type, stream)?;
+        }
+        Ok(())
+    })?;
+    Ok((items, count))
+}
+
+pub(crate) fn parse_enum_item<'a>(
+    len: usize,
+    base_data_type: AdsDataType,
+    stream: &mut Reader<'a>,
+) -> Result<EnumItemRef<'a>, DecodeError> {
+    let name_len = stream.get_u8()? as usize;
+    let name = stream.get_strz(name_len)?;
+
+    let offset = stream.offset();
+    let raw = stream.take(len)?;
+    if raw.len() > 8 {
+        return Err(DecodeError::LengthMismatch {
+            offset,
+            expected: 8,
+            actual: raw.len(),
+        });
+    }
+    let value = integer_value(base_data_type, raw);
+
+    Ok(EnumItemRef { name, value })
+}
+
+/// Size of the header as far as it is understood.
+const HEADER_LEN: u16 = 64;
+
+/// Alignment of the top level symbol and data type records.
+const RECORD_ALIGN: usize = 8;
+
+fn encode(symbol_stream: &SymbolStream, stream: &mut Writer) {
+    let header = &symbol_stream.header;
+    let start = stream.len();
+    let header_len = header.header_len.max(HEADER_LEN);
+    stream.set_code_page(symbol_stream.code_page());
+
+    stream.put_u8(header.version.major);
+    stream.put_u8(header.version.minor);
+    stream.put_u16_le(header_len);
+    stream.put_u32_le(count(symbol_stream.symbols.len()));
+    let symbol_data_len_at = stream.len();
+    stream.put_u32_le(0);
+    stream.put_u32_le(count(symbol_stream.data_types.len()));
+    let data_type_data_len_at = stream.len();
+    stream.put_u32_le(0);
+    stream.put_u32_le(header.used_dynamic_symbols);
+    stream.put_u32_le(header.code_page);
+    stream.put_u32_le(header.flags.bits());
+
+    // Reserved
+    stream.put_zeros(16);
+
+    stream.put_guid(header.layout);
+    stream.put_zeros(header_len as usize - (stream.len() - start));
+
+    let symbols_start = stream.len();
+    for symbol in &symbol_stream.symbols {
+        stream.put_record(RECORD_ALIGN, |stream| encode_symbol(symbol, stream));
+    }
+    stream.set_u32_le(symbol_data_len_at, count(stream.len() - symbols_start));
+
+    let data_types_start = stream.len();
+    for data_type in &symbol_stream.data_types {
+        stream.put_record(RECORD_ALIGN, |stream| encode_data_type(data_type, stream));
+    }
+    stream.set_u32_le(data_type_data_len_at, count(stream.len() - data_types_start));
+}
+
+fn count(len: usize) -> u32 {
+    u32::try_from(len).expect("too many items for a u32 length field")
+}
+
+fn count_u16(len: usize) -> u16 {
+    u16::try_from(len).expect("too many items for a u16 length field")
+}
+
+fn count_u8(len: usize) -> u8 {
+    u8::try_from(len).expect("too many items for a u8 length field")
+}
+
+fn encode_symbol(symbol: &Symbol, stream: &mut Writer) {
+    stream.put_u32_le(symbol.index_group);
+    stream.put_u32_le(symbol.index_offset);
+    stream.put_u32_le(count(symbol.len));
+    stream.put_u32_le(symbol.data_type as u32);
+    stream.put_u32_le(symbol.flags.bits());
+
+    let name = stream.text(&symbol.name);
+    let data_type_name = stream.text(&symbol.data_type_name);
+    let comment = stream.text(&symbol.comment);
+    stream.put_u16_le(count_u16(name.len()));
+    stream.put_u16_le(count_u16(data_type_name.len()));
+    stream.put_u16_le(count_u16(comment.len()));
+    stream.put_strz(&name);
+    stream.put_strz(&data_type_name);
+    stream.put_strz(&comment);
+
+    if symbol.flags.contains(SymbolFlags::TYPE_GUID) {
+        stream.put_guid(symbol.data_type_guid.unwrap_or_default());
+    }
+
+    if symbol.flags.contains(SymbolFlags::ATTRIBUTES) {
+        encode_attributes(&symbol.attributes, stream);
+    }
+}
+
+fn encode_data_type(data_type: &DataType, stream: &mut Writer) {
+    stream.put_u32_le(data_type.version);
+    stream.put_u32_le(data_type.hash_value);
+    stream.put_u32_le(data_type.type_hash_value);
+    stream.put_u32_le(data_type.data_type_len);
+    stream.put_u32_le(data_type.offset);
+    stream.put_u32_le(data_type.base_data_type as u32);
+    stream.put_u32_le(data_type.flags.bits());
+
+    let name = stream.text(&data_type.name);
+    let data_type_name = stream.text(&data_type.data_type_name);
+    let comment = stream.text(&data_type.comment);
+    stream.put_u16_le(count_u16(name.len()));
+    stream.put_u16_le(count_u16(data_type_name.len()));
+    stream.put_u16_le(count_u16(comment.len()));
+    stream.put_u16_le(count_u16(data_type.array_dimensions.len()));
+    stream.put_u16_le(count_u16(data_type.sub_items.len()));
+    stream.put_strz(&name);
+    stream.put_strz(&data_type_name);
+    stream.put_strz(&comment);
+
+    for dimension in &data_type.array_dimensions {
+        stream.put_u32_le(dimension.lower_bound as u32);
+        stream.put_u32_le(dimension.element_count);
+    }
+
+    for sub_item in &data_type.sub_items {
+        stream.put_record(1, |stream| encode_data_type(sub_item, stream));
+    }
+
+    if data_type.flags.contains(DataTypeFlags::TYPE_GUID) {
+        stream.put_guid(data_type.guid.unwrap_or_default());
+    }
+
+    if data_type.flags.contains(DataTypeFlags::COPY_MASK) {
+        let mask = data_type.copy_mask.as_ref().map_or(&[][..], |mask| &mask.0[..]);
+        let len = data_type.data_type_len as usize;
+        stream.put_slice(&mask[..mask.len().min(len)]);
+        stream.put_zeros(len.saturating_sub(mask.len()));
+    }
+
+    if data_type.flags.contains(DataTypeFlags::METHOD_INFOS) {
+        encode_methods(&data_type.methods, stream);
+    }
+
+    if data_type.flags.contains(DataTypeFlags::ATTRIBUTES) {
+        encode_attributes(&data_type.attributes, stream);
+    }
+
+    if data_type.flags.contains(DataTypeFlags::ENUM_INFOS) {
+        encode_enum_items(data_type.data_type_len as usize, &data_type.enum_items, stream);
+    }
+}
+
+fn encode_methods(methods: &[Method], stream: &mut Writer) {
+    stream.put_u16_le(count_u16(methods.len()));
+    for method in methods {
+        stream.put_record(1, |stream| encode_method(method, stream));
+    }
+}
+
+fn encode_method(method: &Method, stream: &mut Writer) {
+    stream.put_u32_le(method.version);
+    stream.put_u32_le(method.vtable_index);
+    stream.put_u32_le(method.return_size);
+    stream.put_u32_le(method.return_align_size);
+    stream.put_u32_le(method.reserved);
+    stream.put_guid(method.return_type_guid);
+    stream.put_u32_le(method.return_data_type as u32);
+    stream.put_u32_le(method.flags);
+
+    let name = stream.text(&method.name);
+    let return_type = stream.text(&method.return_type);
+    let comment = stream.text(&method.comment);
+    stream.put_u16_le(count_u16(name.len()));
+    stream.put_u16_le(count_u16(return_type.len()));
+    stream.put_u16_le(count_u16(comment.len()));
+    stream.put_u16_le(count_u16(method.parameters.len()));
+    stream.put_strz(&name);
+    stream.put_strz(&return_type);
+    stream.put_strz(&comment);
+
+    for parameter in &method.parameters {
+        stream.put_record(1, |stream| encode_method_parameter(parameter, stream));
+    }
+}
+
+fn encode_method_parameter(parameter: &MethodParameter, stream: &mut Writer) {
+    stream.put_u32_le(parameter.size);
+    stream.put_u32_le(parameter.align_size);
+    stream.put_u32_le(parameter.data_type as u32);
+    stream.put_u32_le(parameter.flags.bits());
+    stream.put_u32_le(parameter.reserved);
+    stream.put_guid(parameter.type_guid);
+    stream.put_u16_le(parameter.length_is_parameter);
+
+    let name = stream.text(&parameter.name);
+    let type_name = stream.text(&parameter.type_name);
+    let comment = stream.text(&parameter.comment);
+    stream.put_u16_le(count_u16(name.len()));
+    stream.put_u16_le(count_u16(type_name.len()));
+    stream.put_u16_le(count_u16(comment.len()));
+    stream.put_strz(&name);
+    stream.put_strz(&type_name);
+    stream.put_strz(&comment);
+}
+
+fn encode_attributes(attributes: &Attributes, stream: &mut Writer) {
+    stream.put_u16_le(count_u16(attributes.len()));
+    for attribute in attributes {
+        let key = stream.text(&attribute.key);
+        let value = stream.text(&attribute.value);
+        stream.put_u8(count_u8(key.len()));
+        stream.put_u8(count_u8(value.len()));
+        stream.put_strz(&key);
+        stream.put_strz(&value);
+    }
+}
+
+fn encode_enum_items(len: usize, items: &[EnumItem], stream: &mut Writer) {
+    stream.put_u16_le(count_u16(items.len()));
+    for item in items {
+        let name = stream.text(&item.name);
+        stream.put_u8(count_u8(name.len()));
+        stream.put_strz(&name);
+
+        let value = item.value.to_le_bytes();
+        stream.put_slice(&value[..len.min(8)]);
+        stream.put_zeros(len.saturating_sub(8));
+    }
+}
@@ -0,0 +1,317 @@
+use std::borrow::Cow;
+use std::fmt;
+
+use uuid::Uuid;
+
+use crate::ads::AdsDataType;
+use crate::error::DecodeError;
+use crate::reader::Reader;
+use crate::symbol_stream::{
+    parse_attribute, parse_data_type, parse_enum_item, parse_symbol, ArrayDimension, Attribute,
+    Attributes, CopyMask, DataType, DataTypeFlags, EnumItem, Method, Symbol, SymbolFlags,
+    SymbolStream, SymbolStreamHeader,
+};
+use crate::text::TextMode;
+
+/// A symbol stream decoded in place, borrowing names and comments from the input where they
+/// need no conversion from its code page.
+///
+/// Only the header is decoded up front. Symbols and data types are decoded one at a time as the
+/// iterators returned by [`symbols`](SymbolStreamRef::symbols) and
+/// [`data_types`](SymbolStreamRef::data_types) advance, which makes scanning large streams
+/// cheap. [`into_owned`](SymbolStreamRef::into_owned) converts to a [`SymbolStream`].
+#[derive(Debug, Clone)]
+pub struct SymbolStreamRef<'a> {
+    pub header: SymbolStreamHeader,
+    pub(crate) symbol_data: Reader<'a>,
+    pub(crate) data_type_data: Reader<'a>,
+}
+
+/// Lazily decodes a run of length prefixed records. Stops after the first error.
+#[derive(Clone)]
+pub struct Records<'a, T> {
+    stream: Reader<'a>,
+    remaining: usize,
+    /// How deep the records are nested in other records, handed to `parse`.
+    depth: usize,
+    parse: fn(&mut Reader<'a>, usize) -> Result<T, DecodeError>,
+}
+
+#[derive(Debug, Clone)]
+pub struct SymbolRef<'a> {
+    pub index_group: u32,
+    pub index_offset: u32,
+    pub len: usize,
+    pub data_type: AdsDataType,
+    pub flags: SymbolFlags,
+    pub name: Cow<'a, str>,
+    pub data_type_name: Cow<'a, str>,
+    pub comment: Cow<'a, str>,
+    pub data_type_guid: Option<Uuid>,
+    pub attributes: AttributesRef<'a>,
+}
+
+/// A data type decoded in place. See [`DataType`] for the meaning of the fields.
+#[derive(Debug, Clone)]
+pub struct DataTypeRef<'a> {
+    pub version: u32,
+    pub hash_value: u32,
+    pub type_hash_value: u32,
+    pub data_type_len: u32,
+    pub offset: u32,
+    pub base_data_type: AdsDataType,
+    pub flags: DataTypeFlags,
+    pub array_dimension_count: u16,
+    pub sub_item_count: u16,
+    pub name: Cow<'a, str>,
+    pub data_type_name: Cow<'a, str>,
+    pub comment: Cow<'a, str>,
+    pub guid: Option<Uuid>,
+    pub copy_mask: Option<&'a [u8]>,
+    pub attributes: AttributesRef<'a>,
+    pub(crate) array_dimensions: &'a [u8],
+    pub(crate) sub_items: Records<'a, DataTypeRef<'a>>,
+    pub(crate) methods: Records<'a, Method>,
+    pub(crate) enum_items: Reader<'a>,
+    pub(crate) enum_item_count: u16,
+}
+
+/// Attributes of a symbol or data type, decoded in place.
+#[derive(Debug, Clone, Default)]
+pub struct AttributesRef<'a> {
+    pub(crate) data: Reader<'a>,
+    pub(crate) count: u16,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AttributeRef<'a> {
+    pub key: Cow<'a, str>,
+    pub value: Cow<'a, str>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EnumItemRef<'a> {
+    pub name: Cow<'a, str>,
+    pub value: i64,
+}
+
+impl<'a> SymbolStreamRef<'a> {
+    /// Decodes the header of a symbol stream and locates its symbols and data types. Text must
+    /// be valid in the header's code page.
+    pub fn parse(data: &'a [u8]) -> Result<SymbolStreamRef<'a>, DecodeError> {
+        SymbolStreamRef::parse_with(data, TextMode::Strict)
+    }
+
+    /// Like [`parse`](SymbolStreamRef::parse), handling malformed text as `mode` says.
+    pub fn parse_with(data: &'a [u8], mode: TextMode) -> Result<SymbolStreamRef<'a>, DecodeError> {
+        let mut stream = Reader::new(data);
+        crate::symbol_stream::parse(&mut stream, mode)
+    }
+
+    pub fn symbols(&self) -> Records<'a, SymbolRef<'a>> {
+        Records::new(
+            self.symbol_data.clone(),
+            self.header.symbol_count as usize,
+            0,
+            |stream, _| parse_symbol(stream),
+        )
+    }
+
+    pub fn data_types(&self) -> Records<'a, DataTypeRef<'a>> {
+        Records::new(
+            self.data_type_data.clone(),
+            self.header.data_type_count as usize,
+            0,
+            parse_data_type,
+        )
+    }
+
+    /// Decodes all symbols and data types into owned values.
+    pub fn into_owned(self) -> Result<SymbolStream, DecodeError> {
+        let symbols = self
+            .symbols()
+            .map(|symbol| symbol.map(SymbolRef::into_owned))
+            .collect::<Result<_, _>>()?;
+        let data_types = self
+            .data_types()
+            .map(|data_type| data_type?.into_owned())
+            .collect::<Result<_, _>>()?;
+
+        Ok(SymbolStream {
+            header: self.header,
+            symbols,
+            data_types,
+        })
+    }
+}
+
+impl<'a, T> Records<'a, T> {
+    pub(crate) fn new(
+        stream: Reader<'a>,
+        count: usize,
+        depth: usize,
+        parse: fn(&mut Reader<'a>, usize) -> Result<T, DecodeError>,
+    ) -> Self {
+        Records {
+            stream,
+            remaining: count,
+            depth,
+            parse,
+        }
+    }
+}
+
+impl<'a, T> Iterator for Records<'a, T> {
+    type Item = Result<T, DecodeError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.remaining == 0 {
+            return None;
+        }
+        self.remaining -= 1;
+
+        let record = crate::symbol_stream::next_record(&mut self.stream)
+            .and_then(|mut record| (self.parse)(&mut record, self.depth));
+        if record.is_err() {
+            self.remaining = 0;
+        }
+        Some(record)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (0, Some(self.remaining))
+    }
+}
+
+impl<T> fmt::Debug for Records<'_, T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Records")
+            .field("offset", &self.stream.offset())
+            .field("remaining", &self.remaining)
+            .finish()
+    }
+}
+
+impl SymbolRef<'_> {
+    pub fn into_owned(self) -> Symbol {
+        Symbol {
+            index_group: self.index_group,
+            index_offset: self.index_offset,
+            len: self.len,
+            data_type: self.data_type,
+            flags: self.flags,
+            name: self.name.into_owned(),
+            data_type_name: self.data_type_name.into_owned(),
+            comment: self.comment.into_owned(),
+            data_type_guid: self.data_type_guid,
+            attributes: self.attributes.into_owned(),
+        }
+    }
+}
+
+impl<'a> DataTypeRef<'a> {
+    pub fn array_dimensions(&self) -> impl Iterator<Item = ArrayDimension> + 'a {
+        self.array_dimensions
+            .chunks_exact(8)
+            .map(|raw| ArrayDimension {
+                lower_bound: i32::from_le_bytes(raw[..4].try_into().unwrap()),
+                element_count: u32::from_le_bytes(raw[4..].try_into().unwrap()),
+            })
+    }
+
+    /// Members of a struct or function block, decoded as the iterator advances. Members nested
+    /// deeper than a decoder follows are reported as [`DecodeError::NestingTooDeep`].
+    pub fn sub_items(&self) -> Records<'a, DataTypeRef<'a>> {
+        self.sub_items.clone()
+    }
+
+    pub fn methods(&self) -> Records<'a, Method> {
+        self.methods.clone()
+    }
+
+    pub fn enum_items(&self) -> impl Iterator<Item = EnumItemRef<'a>> + 'a {
+        let mut stream = self.enum_items.clone();
+        let len = self.data_type_len as usize;
+        let base_data_type = self.base_data_type;
+        (0..self.enum_item_count).map(move |_| {
+            parse_enum_item(len, base_data_type, &mut stream)
+                .expect("enumerators are validated when the data type is parsed")
+        })
+    }
+
+    /// Converts to an owned data type, decoding its members and methods.
+    pub fn into_owned(self) -> Result<DataType, DecodeError> {
+        let sub_items = self
+            .sub_items()
+            .map(|sub_item| sub_item?.into_owned())
+            .collect::<Result<_, _>>()?;
+        let methods = self.methods().collect::<Result<_, _>>()?;
+        let array_dimensions = self.array_dimensions().collect();
+        let attributes = self.attributes.clone().into_owned();
+        let enum_items = self
+            .enum_items()
+            .map(|item| EnumItem {
+                name: item.name.into_owned(),
+                value: item.value,
+            })
+            .collect();
+
+        Ok(DataType {
+            version: self.version,
+            hash_value: self.hash_value,
+            type_hash_value: self.type_hash_value,
+            data_type_len: self.data_type_len,
+            offset: self.offset,
+            base_data_type: self.base_data_type,
+            flags: self.flags,
+            array_dimension_count: self.array_dimension_count,
+            sub_item_count: self.sub_item_count,
+            name: self.name.into_owned(),
+            data_type_name: self.data_type_name.into_owned(),
+            comment: self.comment.into_owned(),
+            array_dimensions,
+            sub_items,
+            guid: self.guid,
+            copy_mask: self.copy_mask.map(|mask| CopyMask(mask.to_vec())),
+            methods,
+            attributes,
+            enum_items,
+        })
+    }
+}
+
+impl<'a> AttributesRef<'a> {
+    pub fn len(&self) -> usize {
+        self.count.into()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.count == 0
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = AttributeRef<'a>> + 'a {
+        let mut stream = self.data.clone();
+        (0..self.count).map(move |_| {
+            parse_attribute(&mut stream).expect("attributes are validated when they are parsed")
+        })
+    }
+
+    /// Value of the attribute named `key`, matched case-insensitively like
+    /// [`Attributes::get`].
+    pub fn get(&self, key: &str) -> Option<Cow<'a, str>> {
+        self.iter()
+            .find(|attribute| attribute.key.eq_ignore_ascii_case(key))
+            .map(|attribute| attribute.value)
+    }
+
+    pub fn into_owned(self) -> Attributes {
+        Attributes(
+            self.iter()
+                .map(|attribute| Attribute {
+                    key: attribute.key.into_owned(),
+                    value: attribute.value.into_owned(),
+                })
+                .collect(),
+        )
+    }
+}
@@ -0,0 +1,184 @@
+use std::borrow::Cow;
+use std::fmt;
+
+use encoding_rs::Encoding;
+
+use crate::error::DecodeError;
+
+/// A Windows code page identifier, as found in the symbol stream header.
+///
+/// Names, comments and STRING values are stored in the code page of the PLC project. TwinCAT
+/// 3.1.4024 and later use UTF-8 (65001); older projects typically use Windows-1252.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct CodePage(pub u32);
+
+/// How to handle text that is not valid in its code page.
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq, Hash)]
+pub enum TextMode {
+    /// Fail with [`DecodeError::InvalidString`] on malformed text and with
+    /// [`DecodeError::UnsupportedCodePage`] on code pages that cannot be decoded.
+    #[default]
+    Strict,
+    /// Replace malformed sequences with U+FFFD and decode unsupported code pages as UTF-8.
+    Lossy,
+}
+
+/// The code page and error handling to decode text with.
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq)]
+pub(crate) struct Text {
+    pub code_page: CodePage,
+    pub mode: TextMode,
+}
+
+impl CodePage {
+    pub const UTF_8: CodePage = CodePage(65001);
+    pub const WINDOWS_1252: CodePage = CodePage(1252);
+
+    /// Whether text in this code page can be decoded and encoded.
+    pub fn is_supported(self) -> bool {
+        self.encoding().is_some()
+    }
+
+    fn encoding(self) -> Option<&'static Encoding> {
+        use encoding_rs::*;
+
+        let encoding = match self.0 {
+            874 => WINDOWS_874,
+            932 => SHIFT_JIS,
+            936 => GBK,
+            949 => EUC_KR,
+            950 => BIG5,
+            1250 => WINDOWS_1250,
+            1251 => WINDOWS_1251,
+            1252 => WINDOWS_1252,
+            1253 => WINDOWS_1253,
+            1254 => WINDOWS_1254,
+            1255 => WINDOWS_1255,
+            1256 => WINDOWS_1256,
+            1257 => WINDOWS_1257,
+            1258 => WINDOWS_1258,
+            20866 => KOI8_R,
+            21866 => KOI8_U,
+            28592 => ISO_8859_2,
+            28593 => ISO_8859_3,
+            28594 => ISO_8859_4,
+            28595 => ISO_8859_5,
+            28596 => ISO_8859_6,
+            28597 => ISO_8859_7,
+            28598 => ISO_8859_8,
+            28603 => ISO_8859_13,
+            28605 => ISO_8859_15,
+            54936 => GB18030,
+            65001 => UTF_8,
+            _ => return None,
+        };
+        Some(encoding)
+    }
+
+    /// Encodes `text`. Returns `None` if the code page is not supported or cannot represent
+    /// every character.
+    pub(crate) fn encode(self, text: &str) -> Option<Cow<'_, [u8]>> {
+        let (encoded, _, unmappable) = self.encoding()?.encode(text);
+        (!unmappable).then_some(encoded)
+    }
+
+    /// Encodes as much of `text` as fits into `max_len` bytes without splitting a character.
+    /// Returns `None` if the code page is not supported or cannot represent every character.
+    pub(crate) fn encode_prefix(self, text: &str, max_len: usize) -> Option<Cow<'_, [u8]>> {
+        let encoded = self.encode(text)?;
+        if encoded.len() <= max_len {
+            return Some(encoded);
+        }
+
+        // The supported code pages are stateless, so characters encode independently
+        let mut len = 0;
+        for c in text.chars() {
+            let char_len = self.encode(c.encode_utf8(&mut [0; 4]))?.len();
+            if len + char_len > max_len {
+                break;
+            }
+            len += char_len;
+        }
+        Some(match encoded {
+            Cow::Borrowed(encoded) => Cow::Borrowed(&encoded[..len]),
+            Cow::Owned(mut encoded) => {
+                encoded.truncate(len);
+                Cow::Owned(encoded)
+            }
+        })
+    }
+
+    /// Encodes `text`, writing characters the code page cannot represent as `?`. Unsupported
+    /// code pages are written as UTF-8.
+    pub(crate) fn encode_lossy(self, text: &str) -> Cow<'_, [u8]> {
+        if let Some(encoded) = self.encode(text) {
+            return encoded;
+        }
+        if !self.is_supported() {
+            return Cow::Borrowed(text.as_bytes());
+        }
+
+        let mut encoded = Vec::with_capacity(text.len());
+        for c in text.chars() {
+            match self.encode(c.encode_utf8(&mut [0; 4])) {
+                Some(bytes) => encoded.extend_from_slice(&bytes),
+                None => encoded.push(b'?'),
+            }
+        }
+        Cow::Owned(encoded)
+    }
+}
+
+impl Default for CodePage {
+    fn default() -> Self {
+        CodePage::UTF_8
+    }
+}
+
+impl fmt::Display for CodePage {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.encoding() {
+            Some(encoding) => write!(f, "{} ({})", self.0, encoding.name()),
+            None => write!(f, "{}", self.0),
+        }
+    }
+}
+
+impl Text {
+    /// UTF-8 with malformed sequences replaced, for values decoded without a symbol stream.
+    pub const LOSSY_UTF_8: Text = Text {
+        code_page: CodePage::UTF_8,
+        mode: TextMode::Lossy,
+    };
+
+    /// Decodes `bytes`, all of which belong to the text. Errors report offsets relative to the
+    /// start of `bytes`.
+    pub fn decode(self, bytes: &[u8]) -> Result<Cow<'_, str>, DecodeError> {
+        let encoding = match (self.code_page.encoding(), self.mode) {
+            (Some(encoding), _) => encoding,
+            (None, TextMode::Lossy) => return Ok(String::from_utf8_lossy(bytes)),
+            (None, TextMode::Strict) => {
+                return Err(DecodeError::UnsupportedCodePage {
+                    code_page: self.code_page.0,
+                })
+            }
+        };
+
+        match self.mode {
+            TextMode::Strict => encoding
+                .decode_without_bom_handling_and_without_replacement(bytes)
+                .ok_or(DecodeError::InvalidString { offset: 0 }),
+            TextMode::Lossy => Ok(encoding.decode_without_bom_handling(bytes).0),
+        }
+    }
+
+    /// Decodes UTF-16LE text, for WSTRING values, which do not depend on the code page.
+    pub fn decode_utf16(self, units: &[u16]) -> Result<String, DecodeError> {
+        match self.mode {
+            TextMode::Strict => {
+                String::from_utf16(units).map_err(|_| DecodeError::InvalidString { offset: 0 })
+            }
+            TextMode::Lossy => Ok(String::from_utf16_lossy(units)),
+        }
+    }
+}
@@ -0,0 +1,132 @@
+use std::fmt;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// Seconds from 1601-01-01, the Windows FILETIME epoch, to the Unix epoch.
+const FILETIME_UNIX_OFFSET: i128 = 11_644_473_600;
+
+/// Seconds from the Unix epoch to 2000-01-01, the EtherCAT distributed clock epoch.
+const DC_TIME_UNIX_OFFSET: i128 = 946_684_800;
+
+const NANOS_PER_SEC: i128 = 1_000_000_000;
+
+/// A point in time as carried by a data stream.
+///
+/// Streams count either in Windows FILETIME units, 100 ns since 1601-01-01 UTC, or in EtherCAT
+/// distributed clock units, 1 ns since 2000-01-01 UTC, as selected by
+/// [`DataStreamFlags::DC_TIME`](crate::DataStreamFlags::DC_TIME).
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum Timestamp {
+    FileTime(u64),
+    DcTime(u64),
+}
+
+impl Timestamp {
+    /// Nanoseconds since the Unix epoch, negative for times before it.
+    pub fn unix_nanos(self) -> i128 {
+        match self {
+            Timestamp::FileTime(ticks) => {
+                i128::from(ticks) * 100 - FILETIME_UNIX_OFFSET * NANOS_PER_SEC
+            }
+            Timestamp::DcTime(nanos) => i128::from(nanos) + DC_TIME_UNIX_OFFSET * NANOS_PER_SEC,
+        }
+    }
+
+    /// The count in the timestamp's own unit.
+    pub fn raw(self) -> u64 {
+        match self {
+            Timestamp::FileTime(raw) | Timestamp::DcTime(raw) => raw,
+        }
+    }
+
+    /// The same point in time in FILETIME units, rounded down to 100 ns. `None` if it cannot
+    /// be represented.
+    pub fn to_file_time(self) -> Option<Timestamp> {
+        let ticks = (self.unix_nanos() + FILETIME_UNIX_OFFSET * NANOS_PER_SEC).div_euclid(100);
+        u64::try_from(ticks).ok().map(Timestamp::FileTime)
+    }
+
+    /// The same point in time in distributed clock units. `None` if it lies before 2000 or
+    /// cannot be represented.
+    pub fn to_dc_time(self) -> Option<Timestamp> {
+        let nanos = self.unix_nanos() - DC_TIME_UNIX_OFFSET * NANOS_PER_SEC;
+        u64::try_from(nanos).ok().map(Timestamp::DcTime)
+    }
+
+    pub fn to_system_time(self) -> SystemTime {
+        let nanos = self.unix_nanos();
+        let since_epoch = Duration::new(
+            (nanos.unsigned_abs() / NANOS_PER_SEC as u128) as u64,
+            (nanos.unsigned_abs() % NANOS_PER_SEC as u128) as u32,
+        );
+        if nanos < 0 {
+            UNIX_EPOCH - since_epoch
+        } else {
+            UNIX_EPOCH + since_epoch
+        }
+    }
+
+    #[cfg(feature = "chrono")]
+    pub fn to_chrono(self) -> chrono::DateTime<chrono::Utc> {
+        let nanos = self.unix_nanos();
+        let secs = nanos.div_euclid(NANOS_PER_SEC) as i64;
+        let subsec_nanos = nanos.rem_euclid(NANOS_PER_SEC) as u32;
+        // Both units stay far within chrono's range of about 262,000 years
+        chrono::DateTime::from_timestamp(secs, subsec_nanos).expect("timestamp out of range")
+    }
+
+    /// The timestamp `ticks` 100 ns intervals later, in the same unit.
+    pub(crate) fn add_ticks(self, ticks: u64) -> Timestamp {
+        match self {
+            Timestamp::FileTime(t) => Timestamp::FileTime(t.wrapping_add(ticks)),
+            Timestamp::DcTime(t) => Timestamp::DcTime(t.wrapping_add(ticks.wrapping_mul(100))),
+        }
+    }
+}
+
+impl From<Timestamp> for SystemTime {
+    fn from(timestamp: Timestamp) -> Self {
+        timestamp.to_system_time()
+    }
+}
+
+#[cfg(feature = "chrono")]
+impl From<Timestamp> for chrono::DateTime<chrono::Utc> {
+    fn from(timestamp: Timestamp) -> Self {
+        timestamp.to_chrono()
+    }
+}
+
+impl fmt::Display for Timestamp {
+    /// Formats the timestamp as an RFC 3339 UTC date and time with nanosecond precision.
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let nanos = self.unix_nanos();
+        let secs = nanos.div_euclid(NANOS_PER_SEC);
+        let subsec_nanos = nanos.rem_euclid(NANOS_PER_SEC);
+        let days = secs.div_euclid(86_400);
+        let secs_of_day = secs.rem_euclid(86_400);
+        let (year, month, day) = civil_from_days(days);
+
+        write!(
+            f,
+            "{year:04}-{month:02}-{day:02}T{:02}:{:02}:{:02}.{subsec_nanos:09}Z",
+            secs_of_day / 3600,
+            secs_of_day / 60 % 60,
+            secs_of_day % 60,
+        )
+    }
+}
+
+/// Converts days since the Unix epoch to a proleptic Gregorian calendar date, following
+/// Howard Hinnant's `civil_from_days`.
+fn civil_from_days(days: i128) -> (i128, u32, u32) {
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z.rem_euclid(146_097);
+    let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = (doy - (153 * mp + 2) / 5 + 1) as u32;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 } as u32;
+    let year = yoe + era * 400 + i128::from(month <= 2);
+    (year, month, day)
+}
@@ -0,0 +1,950 @@
+use std::fmt;
+
+use bytes::Buf;
+
+use crate::ads::AdsDataType;
+use crate::error::DecodeError;
+use crate::symbol_stream::{element_count, ArrayDimension, DataType, DataTypeFlags};
+use crate::text::{CodePage, Text};
+
+/// A decoded PLC value.
+#[derive(Debug, Clone, PartialEq)]
+pub enum PlcValue {
+    Bool(bool),
+    SInt(i8),
+    USInt(u8),
+    Int(i16),
+    UInt(u16),
+    DInt(i32),
+    UDInt(u32),
+    LInt(i64),
+    ULInt(u64),
+    Real(f32),
+    LReal(f64),
+    /// An 80 bit extended precision value, rounded to the nearest `f64`.
+    Real80(f64),
+    String(String),
+    WString(String),
+    /// Members of a struct or function block, in declaration order.
+    Struct(Vec<(String, PlcValue)>),
+    /// Elements of an array. Multi-dimensional arrays nest one level per dimension.
+    Array(Vec<PlcValue>),
+    Enum {
+        type_name: String,
+        /// Name of the enumerator, `None` if the value is not one of the declared enumerators.
+        name: Option<String>,
+        value: i64,
+    },
+    /// Raw bytes of a value that cannot be interpreted on its own.
+    Bytes(Vec<u8>),
+}
+
+impl PlcValue {
+    /// Decodes a value of a primitive type from the start of `bytes`.
+    ///
+    /// Strings take up all of `bytes` and end at the first NUL character. STRING values are
+    /// taken to be UTF-8 and malformed text is replaced; [`SampleDecoder`] and
+    /// [`SymbolStream::decode_symbol`] decode them in the code page of the symbol stream. Types
+    /// without a fixed representation are returned as [`PlcValue::Bytes`].
+    ///
+    /// [`SampleDecoder`]: crate::SampleDecoder
+    /// [`SymbolStream::decode_symbol`]: crate::SymbolStream::decode_symbol
+    pub fn decode_primitive(data_type: AdsDataType, bytes: &[u8]) -> Result<PlcValue, DecodeError> {
+        decode_primitive(data_type, bytes, Text::LOSSY_UTF_8)
+    }
+
+    /// Decodes a value of `data_type` from the start of `bytes`.
+    ///
+    /// Enumerations, arrays and structs are decoded from the information carried by the data
+    /// type record itself. Members whose type is only referenced by name are decoded from their
+    /// base type, or returned as [`PlcValue::Bytes`]; use [`SymbolStream::decode_symbol`] to
+    /// resolve them.
+    ///
+    /// [`SymbolStream::decode_symbol`]: crate::SymbolStream::decode_symbol
+    pub fn decode(data_type: &DataType, bytes: &[u8]) -> Result<PlcValue, DecodeError> {
+        decode_type(data_type, bytes, &[][..], Text::LOSSY_UTF_8, 0)
+    }
+
+    /// The value as a floating point number, for numeric and enumeration values.
+    pub fn as_f64(&self) -> Option<f64> {
+        match *self {
+            PlcValue::Real(v) => Some(v.into()),
+            PlcValue::LReal(v) | PlcValue::Real80(v) => Some(v),
+            _ => self.as_i128().map(|v| v as f64),
+        }
+    }
+
+    /// The value as an integer, for boolean, integer and enumeration values.
+    pub fn as_i128(&self) -> Option<i128> {
+        match *self {
+            PlcValue::Bool(v) => Some(v.into()),
+            PlcValue::SInt(v) => Some(v.into()),
+            PlcValue::USInt(v) => Some(v.into()),
+            PlcValue::Int(v) => Some(v.into()),
+            PlcValue::UInt(v) => Some(v.into()),
+            PlcValue::DInt(v) => Some(v.into()),
+            PlcValue::UDInt(v) => Some(v.into()),
+            PlcValue::LInt(v) => Some(v.into()),
+            PlcValue::ULInt(v) => Some(v.into()),
+            PlcValue::Enum { value, .. } => Some(value.into()),
+            _ => None,
+        }
+    }
+
+    /// The text of a string, or the enumerator name of an enumeration value.
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            PlcValue::String(s) | PlcValue::WString(s) => Some(s),
+            PlcValue::Enum { name, .. } => name.as_deref(),
+            _ => None,
+        }
+    }
+
+    /// The value of the member called `name`, for struct values.
+    pub fn member(&self, name: &str) -> Option<&PlcValue> {
+        match self {
+            PlcValue::Struct(members) => members
+                .iter()
+                .find(|(member, _)| member == name)
+                .map(|(_, value)| value),
+            _ => None,
+        }
+    }
+}
+
+/// Looks up the data types that compound types refer to by name.
+pub(crate) trait TypeLookup {
+    fn data_type(&self, name: &str) -> Option<&DataType>;
+}
+
+impl TypeLookup for [DataType] {
+    fn data_type(&self, name: &str) -> Option<&DataType> {
+        self.iter().find(|data_type| data_type.name == name)
+    }
+}
+
+/// Longest chain of aliases followed before giving up, which guards against cyclic definitions.
+const MAX_ALIAS_DEPTH: usize = 16;
+
+/// Deepest nesting of members and elements decoded or encoded, which guards against types that
+/// contain themselves. Sub item records nested in the records of a symbol stream are followed
+/// as deep.
+pub(crate) const MAX_TYPE_DEPTH: usize = 64;
+
+/// Follows an alias such as `TYPE T_Alias : TestStruct; END_TYPE` to the type it names. Aliases
+/// of primitive types carry their own base type and need no resolving.
+fn resolve_alias<'a, T>(mut data_type: &'a DataType, types: &'a T) -> &'a DataType
+where
+    T: TypeLookup + ?Sized,
+{
+    for _ in 0..MAX_ALIAS_DEPTH {
+        let is_alias = data_type.base_data_type == AdsDataType::BigType
+            && !(data_type.is_enum() || data_type.is_array() || data_type.is_struct())
+            && !data_type.data_type_name.is_empty();
+        match types.data_type(&data_type.data_type_name) {
+            Some(target) if is_alias && target.name != data_type.name => data_type = target,
+            _ => break,
+        }
+    }
+    data_type
+}
+
+/// Decodes a value of `data_type`, resolving the types it refers to by name through `types`.
+/// `depth` counts the enclosing structs and arrays.
+pub(crate) fn decode_type<T>(
+    data_type: &DataType,
+    bytes: &[u8],
+    types: &T,
+    text: Text,
+    depth: usize,
+) -> Result<PlcValue, DecodeError>
+where
+    T: TypeLookup + ?Sized,
+{
+    if depth > MAX_TYPE_DEPTH {
+        return Err(DecodeError::NestingTooDeep { offset: 0 });
+    }
+    let data_type = resolve_alias(data_type, types);
+    let len = data_type.data_type_len as usize;
+    let bytes = bytes.get(..len).ok_or_else(|| DecodeError::Truncated {
+        offset: bytes.len(),
+        needed: len.saturating_sub(bytes.len()),
+    })?;
+
+    if data_type.is_enum() {
+        let value = integer_value(data_type.base_data_type, bytes);
+        return Ok(PlcValue::Enum {
+            type_name: data_type.name.clone(),
+            name: data_type.enum_name(value).map(str::to_string),
+            value,
+        });
+    }
+
+    if data_type.is_array() {
+        let count = data_type
+            .element_count()
+            .ok_or(DecodeError::InvalidArrayBounds { offset: 0 })?;
+        let len = data_type.data_type_len as usize;
+        let stride = len / count.max(1);
+        // More elements than bytes would lay every element over the same bytes
+        if count > 0 && stride == 0 {
+            return Err(DecodeError::InvalidArrayBounds { offset: 0 });
+        }
+        // Elements must fill the array exactly, or they would be read at the wrong offsets
+        if count * stride != len {
+            return Err(DecodeError::LengthMismatch {
+                offset: 0,
+                expected: count * stride,
+                actual: len,
+            });
+        }
+        let element_type = types
+            .data_type(&data_type.data_type_name)
+            .filter(|element_type| element_type.data_type_len as usize == stride);
+        let elements = bytes
+            .chunks_exact(stride.max(1))
+            .enumerate()
+            .map(|(i, element)| {
+                match element_type {
+                    Some(element_type) => {
+                        decode_type(element_type, element, types, text, depth + 1)
+                    }
+                    None => decode_sized(data_type.base_data_type, element, text),
+                }
+                .map_err(|e| e.at_offset(i * stride))
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        return Ok(nest(elements, &data_type.array_dimensions));
+    }
+
+    if data_type.is_struct() {
+        let members = data_type
+            .sub_items
+            .iter()
+            .map(|member| {
+                let value = decode_member(member, bytes, types, text, depth + 1)?;
+                Ok((member.name.clone(), value))
+            })
+            .collect::<Result<_, DecodeError>>()?;
+        return Ok(PlcValue::Struct(members));
+    }
+
+    decode_sized(data_type.base_data_type, bytes, text)
+}
+
+/// Decodes a member of a struct from the bytes of the whole struct.
+fn decode_member<T>(
+    member: &DataType,
+    parent: &[u8],
+    types: &T,
+    text: Text,
+    depth: usize,
+) -> Result<PlcValue, DecodeError>
+where
+    T: TypeLookup + ?Sized,
+{
+    let offset = member.offset as usize;
+
+    if member.flags.contains(DataTypeFlags::BIT_VALUE) {
+        let byte = *parent
+            .get(offset / 8)
+            .ok_or_else(|| DecodeError::Truncated {
+                offset: parent.len(),
+                needed: offset / 8 + 1 - parent.len(),
+            })?;
+        return Ok(PlcValue::Bool(byte & (1 << (offset % 8)) != 0));
+    }
+
+    let len = member.data_type_len as usize;
+    let bytes = parent
+        .get(offset..offset + len)
+        .ok_or_else(|| DecodeError::Truncated {
+            offset: parent.len(),
+            needed: (offset + len).saturating_sub(parent.len()),
+        })?;
+
+    // Members only carry their own layout when it is declared inline; otherwise the type is
+    // looked up by name, provided it has the size the member occupies.
+    let data_type = if member.is_enum() || member.is_array() || member.is_struct() {
+        Some(member)
+    } else {
+        types
+            .data_type(&member.data_type_name)
+            .filter(|data_type| data_type.data_type_len == member.data_type_len)
+    };
+    match data_type {
+        Some(data_type) => decode_type(data_type, bytes, types, text, depth),
+        None => decode_sized(member.base_data_type, bytes, text),
+    }
+    .map_err(|e| e.at_offset(offset))
+}
+
+/// Decodes a value known only by its base type, taking up all of `bytes`. Values whose size does
+/// not match the base type, such as arrays, are returned as [`PlcValue::Bytes`].
+pub(crate) fn decode_sized(
+    data_type: AdsDataType,
+    bytes: &[u8],
+    text: Text,
+) -> Result<PlcValue, DecodeError> {
+    match data_type.fixed_size() {
+        Some(size) if size != bytes.len() => Ok(PlcValue::Bytes(bytes.to_vec())),
+        _ => decode_primitive(data_type, bytes, text),
+    }
+}
+
+/// Decodes a value of a primitive type, see [`PlcValue::decode_primitive`], with STRING values in
+/// the code page of `text`.
+fn decode_primitive(
+    data_type: AdsDataType,
+    bytes: &[u8],
+    text: Text,
+) -> Result<PlcValue, DecodeError> {
+    if let Some(size) = data_type.fixed_size() {
+        if bytes.len() < size {
+            return Err(DecodeError::Truncated {
+                offset: bytes.len(),
+                needed: size - bytes.len(),
+            });
+        }
+    }
+
+    let mut buf = bytes;
+    let value = match data_type {
+        AdsDataType::Bit => PlcValue::Bool(buf.get_u8() != 0),
+        AdsDataType::Int8 => PlcValue::SInt(buf.get_i8()),
+        AdsDataType::UInt8 => PlcValue::USInt(buf.get_u8()),
+        AdsDataType::Int16 => PlcValue::Int(buf.get_i16_le()),
+        AdsDataType::UInt16 => PlcValue::UInt(buf.get_u16_le()),
+        AdsDataType::Int32 => PlcValue::DInt(buf.get_i32_le()),
+        AdsDataType::UInt32 => PlcValue::UDInt(buf.get_u32_le()),
+        AdsDataType::Int64 => PlcValue::LInt(buf.get_i64_le()),
+        AdsDataType::UInt64 => PlcValue::ULInt(buf.get_u64_le()),
+        AdsDataType::Real32 => PlcValue::Real(buf.get_f32_le()),
+        AdsDataType::Real64 => PlcValue::LReal(buf.get_f64_le()),
+        AdsDataType::Real80 => {
+            let mantissa = buf.get_u64_le();
+            let sign_exponent = buf.get_u16_le();
+            PlcValue::Real80(extended_to_f64(mantissa, sign_exponent))
+        }
+        AdsDataType::String => {
+            let len = bytes.iter().position(|&b| b == 0).unwrap_or(bytes.len());
+            PlcValue::String(text.decode(&bytes[..len])?.into_owned())
+        }
+        AdsDataType::WString => {
+            let units: Vec<u16> = bytes
+                .chunks_exact(2)
+                .map(|c| u16::from_le_bytes([c[0], c[1]]))
+                .take_while(|&c| c != 0)
+                .collect();
+            PlcValue::WString(text.decode_utf16(&units)?)
+        }
+        AdsDataType::Void | AdsDataType::MaxTypes | AdsDataType::BigType => {
+            PlcValue::Bytes(bytes.to_vec())
+        }
+    };
+    Ok(value)
+}
+
+/// Writes `value` as a value of `data_type` at the start of `out`, resolving the types it refers
+/// to by name through `types`. Members missing from a struct value are left untouched. `depth`
+/// counts the enclosing structs and arrays.
+///
+/// Returns `None` if the value cannot be represented as that type, or the type nests deeper than
+/// [`MAX_TYPE_DEPTH`].
+pub(crate) fn encode_type<T>(
+    data_type: &DataType,
+    value: &PlcValue,
+    out: &mut [u8],
+    types: &T,
+    code_page: CodePage,
+    depth: usize,
+) -> Option<()>
+where
+    T: TypeLookup + ?Sized,
+{
+    if depth > MAX_TYPE_DEPTH {
+        return None;
+    }
+    let data_type = resolve_alias(data_type, types);
+    let out = out.get_mut(..data_type.data_type_len as usize)?;
+
+    if let PlcValue::Bytes(bytes) = value {
+        return (bytes.len() == out.len()).then(|| out.copy_from_slice(bytes));
+    }
+
+    if data_type.is_enum() {
+        let value = match value {
+            PlcValue::String(name) => data_type.enum_value(name)?,
+            _ => i64::try_from(value.as_i128()?).ok()?,
+        };
+        return encode_sized(
+            data_type.base_data_type,
+            &PlcValue::LInt(value),
+            out,
+            code_page,
+        );
+    }
+
+    if data_type.is_array() {
+        let count = data_type.element_count()?;
+        let len = data_type.data_type_len as usize;
+        let stride = len / count.max(1);
+        if (count > 0 && stride == 0) || count * stride != len {
+            return None;
+        }
+        let element_type = types
+            .data_type(&data_type.data_type_name)
+            .filter(|element_type| element_type.data_type_len as usize == stride);
+        let mut elements = vec![];
+        flatten(value, data_type.array_dimensions.len(), &mut elements)?;
+        if elements.len() != count {
+            return None;
+        }
+        for (element, out) in elements
+            .into_iter()
+            .zip(out.chunks_exact_mut(stride.max(1)))
+        {
+            match element_type {
+                Some(element_type) => {
+                    encode_type(element_type, element, out, types, code_page, depth + 1)?
+                }
+                None => encode_sized(data_type.base_data_type, element, out, code_page)?,
+            }
+        }
+        return Some(());
+    }
+
+    if data_type.is_struct() {
+        let PlcValue::Struct(members) = value else {
+            return None;
+        };
+        for member in &data_type.sub_items {
+            if let Some((_, value)) = members.iter().find(|(name, _)| *name == member.name) {
+                encode_member(member, value, out, types, code_page, depth + 1)?;
+            }
+        }
+        return Some(());
+    }
+
+    encode_sized(data_type.base_data_type, value, out, code_page)
+}
+
+/// Writes a member of a struct into the bytes of the whole struct.
+fn encode_member<T>(
+    member: &DataType,
+    value: &PlcValue,
+    parent: &mut [u8],
+    types: &T,
+    code_page: CodePage,
+    depth: usize,
+) -> Option<()>
+where
+    T: TypeLookup + ?Sized,
+{
+    let offset = member.offset as usize;
+
+    if member.flags.contains(DataTypeFlags::BIT_VALUE) {
+        let byte = parent.get_mut(offset / 8)?;
+        let mask = 1 << (offset % 8);
+        match value.as_i128()? {
+            0 => *byte &= !mask,
+            _ => *byte |= mask,
+        }
+        return Some(());
+    }
+
+    let len = member.data_type_len as usize;
+    let out = parent.get_mut(offset..offset + len)?;
+    let data_type = if member.is_enum() || member.is_array() || member.is_struct() {
+        Some(member)
+    } else {
+        types
+            .data_type(&member.data_type_name)
+            .filter(|data_type| data_type.data_type_len == member.data_type_len)
+    };
+    match data_type {
+        Some(data_type) => encode_type(data_type, value, out, types, code_page, depth),
+        None => encode_sized(member.base_data_type, value, out, code_page),
+    }
+}
+
+/// Writes a value known only by its base type, filling all of `out`. Integer values must be in
+/// range for the type; strings are truncated to fit and padded with NUL characters. STRING
+/// values are encoded in `code_page`, WSTRING values as UTF-16LE.
+///
+/// Returns `None` if the value cannot be represented as that type.
+pub(crate) fn encode_sized(
+    data_type: AdsDataType,
+    value: &PlcValue,
+    out: &mut [u8],
+    code_page: CodePage,
+) -> Option<()> {
+    if let PlcValue::Bytes(bytes) = value {
+        return (bytes.len() == out.len()).then(|| out.copy_from_slice(bytes));
+    }
+    if data_type.fixed_size().is_some_and(|size| size != out.len()) {
+        return None;
+    }
+
+    match data_type {
+        AdsDataType::Bit => out[0] = u8::from(value.as_i128()? != 0),
+        AdsDataType::Int8 => {
+            out.copy_from_slice(&i8::try_from(value.as_i128()?).ok()?.to_le_bytes())
+        }
+        AdsDataType::UInt8 => {
+            out.copy_from_slice(&u8::try_from(value.as_i128()?).ok()?.to_le_bytes())
+        }
+        AdsDataType::Int16 => {
+            out.copy_from_slice(&i16::try_from(value.as_i128()?).ok()?.to_le_bytes())
+        }
+        AdsDataType::UInt16 => {
+            out.copy_from_slice(&u16::try_from(value.as_i128()?).ok()?.to_le_bytes())
+        }
+        AdsDataType::Int32 => {
+            out.copy_from_slice(&i32::try_from(value.as_i128()?).ok()?.to_le_bytes())
+        }
+        AdsDataType::UInt32 => {
+            out.copy_from_slice(&u32::try_from(value.as_i128()?).ok()?.to_le_bytes())
+        }
+        AdsDataType::Int64 => {
+            out.copy_from_slice(&i64::try_from(value.as_i128()?).ok()?.to_le_bytes())
+        }
+        AdsDataType::UInt64 => {
+            out.copy_from_slice(&u64::try_from(value.as_i128()?).ok()?.to_le_bytes())
+        }
+        AdsDataType::Real32 => out.copy_from_slice(&(value.as_f64()? as f32).to_le_bytes()),
+        AdsDataType::Real64 => out.copy_from_slice(&value.as_f64()?.to_le_bytes()),
+        AdsDataType::Real80 => out.copy_from_slice(&f64_to_extended(value.as_f64()?)),
+        AdsDataType::String => {
+            // Leave room for the terminator
+            let text = code_page.encode_prefix(value.as_str()?, out.len().saturating_sub(1))?;
+            out.fill(0);
+            out[..text.len()].copy_from_slice(&text);
+        }
+        AdsDataType::WString => {
+            let text = value.as_str()?;
+            out.fill(0);
+            let capacity = (out.len() / 2).saturating_sub(1);
+            for (unit, out) in text
+                .encode_utf16()
+                .take(capacity)
+                .zip(out.chunks_exact_mut(2))
+            {
+                out.copy_from_slice(&unit.to_le_bytes());
+            }
+        }
+        AdsDataType::Void | AdsDataType::MaxTypes | AdsDataType::BigType => return None,
+    }
+    Some(())
+}
+
+/// Reads a little endian integer of up to eight bytes, sign extending it for signed types.
+pub(crate) fn integer_value(data_type: AdsDataType, bytes: &[u8]) -> i64 {
+    let len = bytes.len().min(8);
+    let mut raw = [0; 8];
+    raw[..len].copy_from_slice(&bytes[..len]);
+    let value = i64::from_le_bytes(raw);
+    if data_type.is_signed() && len > 0 && len < 8 {
+        let shift = 64 - 8 * len;
+        (value << shift) >> shift
+    } else {
+        value
+    }
+}
+
+/// Groups a flat list of elements into one nested array per dimension.
+fn nest(elements: Vec<PlcValue>, dimensions: &[ArrayDimension]) -> PlcValue {
+    match dimensions {
+        [] | [_] => PlcValue::Array(elements),
+        [_, inner @ ..] => {
+            // The inner dimensions can only overflow if an outer one is empty, leaving no rows
+            let chunk = element_count(inner).unwrap_or(usize::MAX);
+            let mut elements = elements.into_iter();
+            let mut rows = vec![];
+            loop {
+                let row: Vec<_> = elements.by_ref().take(chunk.max(1)).collect();
+                if row.is_empty() {
+                    break;
+                }
+                rows.push(nest(row, inner));
+            }
+            PlcValue::Array(rows)
+        }
+    }
+}
+
+/// Collects the elements of a value nested one array level per dimension, in memory order.
+fn flatten<'a>(
+    value: &'a PlcValue,
+    dimensions: usize,
+    elements: &mut Vec<&'a PlcValue>,
+) -> Option<()> {
+    if dimensions == 0 {
+        elements.push(value);
+        return Some(());
+    }
+    let PlcValue::Array(items) = value else {
+        return None;
+    };
+    for item in items {
+        flatten(item, dimensions - 1, elements)?;
+    }
+    Some(())
+}
+
+/// Converts an `f64` to an x87 80 bit extended precision value, which represents it exactly.
+fn f64_to_extended(value: f64) -> [u8; 10] {
+    let bits = value.to_bits();
+    let sign = ((bits >> 63) as u16) << 15;
+    let exponent = ((bits >> 52) & 0x7ff) as u16;
+    let fraction = bits & ((1 << 52) - 1);
+
+    let (exponent, mantissa) = match exponent {
+        0 if fraction == 0 => (0, 0),
+        // Subnormal: normalise, since the extended format has exponent range to spare
+        0 => {
+            let shift = fraction.leading_zeros();
+            (16383 + 63 - 1074 - shift as u16, fraction << shift)
+        }
+        0x7ff => (0x7fff, 1 << 63 | fraction << 11),
+        _ => (exponent + 16383 - 1023, 1 << 63 | fraction << 11),
+    };
+
+    let mut out = [0; 10];
+    out[..8].copy_from_slice(&mantissa.to_le_bytes());
+    out[8..].copy_from_slice(&(sign | exponent).to_le_bytes());
+    out
+}
+
+/// Converts an x87 80 bit extended precision value to the nearest `f64`.
+fn extended_to_f64(mantissa: u64, sign_exponent: u16) -> f64 {
+    let sign = if sign_exponent & 0x8000 != 0 {
+        -1.0
+    } else {
+        1.0
+    };
+    let exponent = i32::from(sign_exponent & 0x7fff);
+
+    if exponent == 0x7fff {
+        return if mantissa << 1 == 0 {
+            sign * f64::INFINITY
+        } else {
+            f64::NAN
+        };
+    }
+
+    // The integer bit is explicit, so the mantissa is a fixed point number with 63 fraction bits
+    let exponent = if exponent == 0 { 1 } else { exponent } - 16383 - 63;
+    let mut value = mantissa as f64;
+    // Apply the exponent in steps so that intermediate results stay finite
+    let mut remaining = exponent;
+    while remaining != 0 {
+        let step = remaining.clamp(-1000, 1000);
+        value *= 2f64.powi(step);
+        remaining -= step;
+    }
+    sign * value
+}
+
+impl fmt::Display for PlcValue {
+    /// Formats the value as an IEC 61131-3 literal.
+    ///
+    /// The standard has no literals for NaN and the infinities, so REAL and LREAL values that
+    /// are not finite are formatted as `<REAL NaN>`, `<LREAL +INF>` or `<LREAL -INF>`, in angle
+    /// brackets that no literal starts with.
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            PlcValue::Bool(v) => f.write_str(if *v { "TRUE" } else { "FALSE" }),
+            PlcValue::SInt(v) => write!(f, "SINT#{v}"),
+            PlcValue::USInt(v) => write!(f, "USINT#{v}"),
+            PlcValue::Int(v) => write!(f, "INT#{v}"),
+            PlcValue::UInt(v) => write!(f, "UINT#{v}"),
+            PlcValue::DInt(v) => write!(f, "DINT#{v}"),
+            PlcValue::UDInt(v) => write!(f, "UDINT#{v}"),
+            PlcValue::LInt(v) => write!(f, "LINT#{v}"),
+            PlcValue::ULInt(v) => write!(f, "ULINT#{v}"),
+            PlcValue::Real(v) if !v.is_finite() => write_non_finite(f, "REAL", (*v).into()),
+            PlcValue::LReal(v) | PlcValue::Real80(v) if !v.is_finite() => {
+                write_non_finite(f, "LREAL", *v)
+            }
+            PlcValue::Real(v) => write!(f, "REAL#{}", real_literal(format!("{v:?}"))),
+            PlcValue::LReal(v) | PlcValue::Real80(v) => {
+                write!(f, "LREAL#{}", real_literal(format!("{v:?}")))
+            }
+            PlcValue::String(s) => {
+                f.write_str("'")?;
+                for c in s.chars() {
+                    match c {
+                        '\'' => f.write_str("$'")?,
+                        _ => write_escaped(f, c, 2)?,
+                    }
+                }
+                f.write_str("'")
+            }
+            PlcValue::WString(s) => {
+                f.write_str("\"")?;
+                for c in s.chars() {
+                    match c {
+                        '"' => f.write_str("$\"")?,
+                        _ => write_escaped(f, c, 4)?,
+                    }
+                }
+                f.write_str("\"")
+            }
+            PlcValue::Struct(members) => {
+                f.write_str("(")?;
+                for (i, (name, value)) in members.iter().enumerate() {
+                    if i > 0 {
+                        f.write_str(", ")?;
+                    }
+                    write!(f, "{name} := {value}")?;
+                }
+                f.write_str(")")
+            }
+            PlcValue::Array(elements) => {
+                f.write_str("[")?;
+                for (i, element) in elements.iter().enumerate() {
+                    if i > 0 {
+                        f.write_str(", ")?;
+                    }
+                    write!(f, "{element}")?;
+                }
+                f.write_str("]")
+            }
+            PlcValue::Enum {
+                type_name,
+                name: Some(name),
+                ..
+            } => write!(f, "{type_name}#{name}"),
+            PlcValue::Enum {
+                type_name,
+                name: None,
+                value,
+            } => write!(f, "{type_name}#{value}"),
+            PlcValue::Bytes(bytes) => {
+                f.write_str("[")?;
+                for (i, byte) in bytes.iter().enumerate() {
+                    if i > 0 {
+                        f.write_str(", ")?;
+                    }
+                    write!(f, "16#{byte:02X}")?;
+                }
+                f.write_str("]")
+            }
+        }
+    }
+}
+
+/// Makes sure a formatted float has the decimal point IEC 61131-3 requires, e.g. `1e20` becomes
+/// `1.0E20`.
+fn real_literal(formatted: String) -> String {
+    match formatted.split_once('e') {
+        Some((mantissa, exponent)) if !mantissa.contains('.') => format!("{mantissa}.0E{exponent}"),
+        Some((mantissa, exponent)) => format!("{mantissa}E{exponent}"),
+        None => formatted,
+    }
+}
+
+/// Writes a NaN or infinite value of the REAL type `type_name`, for which there is no literal.
+fn write_non_finite(f: &mut fmt::Formatter, type_name: &str, value: f64) -> fmt::Result {
+    let value = if value.is_nan() {
+        "NaN"
+    } else if value > 0.0 {
+        "+INF"
+    } else {
+        "-INF"
+    };
+    write!(f, "<{type_name} {value}>")
+}
+
+/// Writes a character of a string literal, using `$` escapes for control characters. `digits`
+/// is the number of hex digits of a character code: 2 for STRING and 4 for WSTRING.
+fn write_escaped(f: &mut fmt::Formatter, c: char, digits: usize) -> fmt::Result {
+    match c {
+        '$' => f.write_str("$$"),
+        '\n' => f.write_str("$N"),
+        '\r' => f.write_str("$R"),
+        '\t' => f.write_str("$T"),
+        '\x0c' => f.write_str("$P"),
+        c if c.is_control() => write!(f, "${:0digits$X}", c as u32),
+        c => write!(f, "{c}"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn round_trip(value: f64) -> f64 {
+        let bytes = f64_to_extended(value);
+        let mantissa = u64::from_le_bytes(bytes[..8].try_into().unwrap());
+        let sign_exponent = u16::from_le_bytes([bytes[8], bytes[9]]);
+        extended_to_f64(mantissa, sign_exponent)
+    }
+
+    #[test]
+    fn extended_round_trip() {
+        let values = [
+            0.0,
+            1.0,
+            -2.5,
+            0.1,
+            std::f64::consts::PI,
+            f64::MAX,
+            f64::MIN_POSITIVE,
+            // Subnormal
+            f64::MIN_POSITIVE / 8.0,
+            -f64::from_bits(1),
+            f64::INFINITY,
+            f64::NEG_INFINITY,
+        ];
+        for value in values {
+            assert_eq!(round_trip(value).to_bits(), value.to_bits(), "{value:e}");
+        }
+
+        assert!(round_trip(f64::NAN).is_nan());
+        assert_eq!(round_trip(-0.0).to_bits(), (-0.0f64).to_bits());
+    }
+
+    #[test]
+    fn extended_layout() {
+        assert_eq!(f64_to_extended(0.0), [0; 10]);
+        assert_eq!(
+            f64_to_extended(1.0),
+            [0, 0, 0, 0, 0, 0, 0, 0x80, 0xFF, 0x3F]
+        );
+        assert_eq!(
+            f64_to_extended(-2.0),
+            [0, 0, 0, 0, 0, 0, 0, 0x80, 0x00, 0xC0]
+        );
+        assert_eq!(
+            f64_to_extended(f64::INFINITY),
+            [0, 0, 0, 0, 0, 0, 0, 0x80, 0xFF, 0x7F]
+        );
+        // Subnormal doubles are normal in the extended format
+        assert_eq!(
+            f64_to_extended(f64::from_bits(1)),
+            [0, 0, 0, 0, 0, 0, 0, 0x80, 0xCD, 0x3B]
+        );
+    }
+
+    #[test]
+    fn extended_precision_loss() {
+        // The 11 low mantissa bits do not fit, and round to the nearest double
+        let one = 1 << 63;
+        assert_eq!(extended_to_f64(one | 1, 0x3FFF), 1.0);
+        assert_eq!(extended_to_f64(one | 1 << 10, 0x3FFF), 1.0);
+        assert_eq!(
+            extended_to_f64(one | 1 << 10 | 1, 0x3FFF),
+            1.0 + f64::EPSILON
+        );
+        assert_eq!(extended_to_f64(u64::MAX, 0x3FFF), 2.0);
+
+        // Out of the range of a double
+        assert_eq!(extended_to_f64(one, 0x3FFF + 1024), f64::INFINITY);
+        assert_eq!(extended_to_f64(one, 0xBFFF + 1024), f64::NEG_INFINITY);
+        assert_eq!(extended_to_f64(one, 0x3FFF - 1100), 0.0);
+        assert_eq!(extended_to_f64(one, 0x3FFF - 1074), f64::from_bits(1));
+
+        // Pseudo-denormal and NaN encodings
+        assert_eq!(extended_to_f64(one, 0), 2f64.powi(-16382));
+        assert!(extended_to_f64(one | 1, 0x7FFF).is_nan());
+        assert!(extended_to_f64(0xC000_0000_0000_0000, 0xFFFF).is_nan());
+    }
+
+    #[test]
+    fn display_numbers() {
+        assert_eq!(PlcValue::Bool(true).to_string(), "TRUE");
+        assert_eq!(PlcValue::SInt(-5).to_string(), "SINT#-5");
+        assert_eq!(
+            PlcValue::ULInt(u64::MAX).to_string(),
+            "ULINT#18446744073709551615"
+        );
+        assert_eq!(PlcValue::Real(1.5).to_string(), "REAL#1.5");
+        assert_eq!(PlcValue::Real(0.1).to_string(), "REAL#0.1");
+        assert_eq!(PlcValue::Real(1e20).to_string(), "REAL#1.0E20");
+        assert_eq!(PlcValue::LReal(-2.5e-300).to_string(), "LREAL#-2.5E-300");
+        assert_eq!(PlcValue::Real80(3.0).to_string(), "LREAL#3.0");
+    }
+
+    #[test]
+    fn display_non_finite() {
+        assert_eq!(PlcValue::Real(f32::NAN).to_string(), "<REAL NaN>");
+        assert_eq!(PlcValue::Real(f32::INFINITY).to_string(), "<REAL +INF>");
+        assert_eq!(PlcValue::LReal(-f64::NAN).to_string(), "<LREAL NaN>");
+        assert_eq!(
+            PlcValue::LReal(f64::NEG_INFINITY).to_string(),
+            "<LREAL -INF>"
+        );
+        assert_eq!(PlcValue::Real80(f64::INFINITY).to_string(), "<LREAL +INF>");
+    }
+
+    #[test]
+    fn display_compound() {
+        assert_eq!(
+            PlcValue::String("it's $5\n\u{1}".into()).to_string(),
+            "'it$'s $$5$N$01'"
+        );
+        assert_eq!(
+            PlcValue::WString("\"Grüße\"\u{7f}".into()).to_string(),
+            "\"$\"Grüße$\"$007F\""
+        );
+        assert_eq!(
+            PlcValue::Struct(vec![
+                ("a".into(), PlcValue::Int(1)),
+                ("b".into(), PlcValue::Array(vec![PlcValue::Bool(false)])),
+            ])
+            .to_string(),
+            "(a := INT#1, b := [FALSE])"
+        );
+        assert_eq!(PlcValue::Array(vec![]).to_string(), "[]");
+        assert_eq!(
+            PlcValue::Enum {
+                type_name: "E_State".into(),
+                name: Some("Running".into()),
+                value: 3,
+            }
+            .to_string(),
+            "E_State#Running"
+        );
+        assert_eq!(
+            PlcValue::Enum {
+                type_name: "E_State".into(),
+                name: None,
+                value: -1,
+            }
+            .to_string(),
+            "E_State#-1"
+        );
+        assert_eq!(PlcValue::Bytes(vec![0, 0xAB]).to_string(), "[16#00, 16#AB]");
+    }
+
+    #[test]
+    fn accessors() {
+        let state = PlcValue::Enum {
+            type_name: "E_State".into(),
+            name: Some("Running".into()),
+            value: 3,
+        };
+
+        assert_eq!(PlcValue::Bool(true).as_i128(), Some(1));
+        assert_eq!(PlcValue::ULInt(u64::MAX).as_i128(), Some(u64::MAX.into()));
+        assert_eq!(state.as_i128(), Some(3));
+        assert_eq!(PlcValue::Real(1.0).as_i128(), None);
+
+        assert_eq!(PlcValue::Real(0.5).as_f64(), Some(0.5));
+        assert_eq!(PlcValue::Real80(-1.25).as_f64(), Some(-1.25));
+        assert_eq!(PlcValue::DInt(-7).as_f64(), Some(-7.0));
+        assert_eq!(state.as_f64(), Some(3.0));
+        assert_eq!(PlcValue::String("1".into()).as_f64(), None);
+
+        assert_eq!(PlcValue::WString("Grüße".into()).as_str(), Some("Grüße"));
+        assert_eq!(state.as_str(), Some("Running"));
+        assert_eq!(PlcValue::Int(1).as_str(), None);
+
+        let value = PlcValue::Struct(vec![("a".into(), PlcValue::Int(1))]);
+        assert_eq!(value.member("a"), Some(&PlcValue::Int(1)));
+        assert_eq!(value.member("b"), None);
+        assert_eq!(PlcValue::Array(vec![]).member("a"), None);
+    }
+}
@@ -0,0 +1,97 @@
+use std::borrow::Cow;
+
+use bytes::BufMut;
+use uuid::Uuid;
+
+use crate::text::CodePage;
+
+/// Little endian output buffer, the counterpart of [`Reader`](crate::reader::Reader). Strings
+/// are encoded in `code_page`.
+#[derive(Debug, Default)]
+pub(crate) struct Writer {
+    buf: Vec<u8>,
+    code_page: CodePage,
+}
+
+impl Writer {
+    pub fn new() -> Self {
+        Writer::default()
+    }
+
+    pub fn set_code_page(&mut self, code_page: CodePage) {
+        self.code_page = code_page;
+    }
+
+    /// Encodes `s` in the writer's code page, replacing characters it cannot represent with
+    /// `?`.
+    pub fn text<'s>(&self, s: &'s str) -> Cow<'s, [u8]> {
+        self.code_page.encode_lossy(s)
+    }
+
+    /// Number of bytes written so far.
+    pub fn len(&self) -> usize {
+        self.buf.len()
+    }
+
+    pub fn into_inner(self) -> Vec<u8> {
+        self.buf
+    }
+
+    pub fn put_u8(&mut self, v: u8) {
+        self.buf.put_u8(v);
+    }
+
+    pub fn put_u16_le(&mut self, v: u16) {
+        self.buf.put_u16_le(v);
+    }
+
+    pub fn put_u32_le(&mut self, v: u32) {
+        self.buf.put_u32_le(v);
+    }
+
+    pub fn put_u64_le(&mut self, v: u64) {
+        self.buf.put_u64_le(v);
+    }
+
+    pub fn put_slice(&mut self, v: &[u8]) {
+        self.buf.put_slice(v);
+    }
+
+    pub fn put_zeros(&mut self, len: usize) {
+        self.buf.put_bytes(0, len);
+    }
+
+    /// Overwrites a little endian `u32` written earlier at absolute offset `at`.
+    pub fn set_u32_le(&mut self, at: usize, v: u32) {
+        self.buf[at..at + 4].copy_from_slice(&v.to_le_bytes());
+    }
+
+    /// Writes a GUID in its Windows memory layout, see
+    /// [`Reader::get_guid`](crate::reader::Reader::get_guid).
+    pub fn put_guid(&mut self, guid: Uuid) {
+        let (data1, data2, data3, data4) = guid.as_fields();
+        self.put_u32_le(data1);
+        self.put_u16_le(data2);
+        self.put_u16_le(data3);
+        self.put_slice(data4);
+    }
+
+    /// Writes an encoded string, see [`text`](Writer::text), followed by a NUL terminator.
+    pub fn put_strz(&mut self, s: &[u8]) {
+        self.put_slice(s);
+        self.put_u8(0);
+    }
+
+    /// Writes a record prefixed with its `u32` length, which includes the prefix itself and any
+    /// zero padding needed to make the length a multiple of `align`.
+    pub fn put_record(&mut self, align: usize, f: impl FnOnce(&mut Writer)) {
+        let start = self.len();
+        self.put_u32_le(0);
+        f(self);
+        let len = self.len() - start;
+        self.put_zeros(len.next_multiple_of(align) - len);
+
+        let len = u32::try_from(self.len() - start).expect("record too long");
+        self.set_u32_le(start, len);
+    }
+}
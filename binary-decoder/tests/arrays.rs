@@ -0,0 +1,179 @@
+//! Converts array indices, and decodes and encodes arrays whose dimensions do not fit their
+//! size.
+
+mod common;
+
+use std::sync::Arc;
+
+use binary_decoder::{
+    ArrayDimension, DataStream, DataStreamWriter, DataType, DecodeError, EncodeError, NamedValue,
+    PlcValue, Sample, SampleDecoder, SymbolStream,
+};
+
+use common::{data_type_mut, ema, symbol};
+
+const INT_ARRAY: &str = "ARRAY [0..7] OF INT";
+
+fn dimension(lower_bound: i32, element_count: u32) -> ArrayDimension {
+    ArrayDimension {
+        lower_bound,
+        element_count,
+    }
+}
+
+/// The ema INT array type with its dimensions replaced by `dimensions`.
+fn int_array(dimensions: Vec<ArrayDimension>) -> DataType {
+    let mut data_type = ema().data_type(INT_ARRAY).unwrap().clone();
+    data_type.array_dimension_count = dimensions.len() as u16;
+    data_type.array_dimensions = dimensions;
+    data_type
+}
+
+/// The ema stream with the dimensions of its INT array replaced by `dimensions`, received from
+/// the wire.
+fn with_dimensions(dimensions: Vec<ArrayDimension>) -> SymbolStream {
+    let mut symbol_stream = ema();
+    let data_type = data_type_mut(&mut symbol_stream, INT_ARRAY);
+    data_type.array_dimension_count = dimensions.len() as u16;
+    data_type.array_dimensions = dimensions;
+    SymbolStream::parse(&symbol_stream.encode()).unwrap()
+}
+
+fn decode_int_array(symbol_stream: &SymbolStream) -> Result<(), DecodeError> {
+    let symbol = symbol(symbol_stream, "Main.p_intArrayValue");
+    symbol_stream.decode_symbol(symbol, &vec![0; symbol.len])?;
+    Ok(())
+}
+
+#[test]
+fn element_count() {
+    let symbol_stream = ema();
+    let data_type = symbol_stream.data_type(INT_ARRAY).unwrap();
+    assert_eq!(data_type.element_count(), Some(8));
+    assert_eq!(data_type.element_stride(), Some(2));
+
+    let data_type = symbol_stream.data_type("INT").unwrap();
+    assert!(!data_type.is_array());
+    assert_eq!(data_type.element_count(), Some(1));
+
+    let data_type = int_array(vec![dimension(0, 2), dimension(0, 0)]);
+    assert_eq!(data_type.element_count(), Some(0));
+    assert_eq!(data_type.element_stride(), Some(0));
+
+    let data_type = int_array(vec![dimension(0, 0x8000_0000); 3]);
+    assert_eq!(data_type.element_count(), None);
+    assert_eq!(data_type.element_stride(), None);
+}
+
+#[test]
+fn upper_bound() {
+    assert_eq!(dimension(0, 8).upper_bound(), Some(7));
+    assert_eq!(dimension(-3, 4).upper_bound(), Some(0));
+    assert_eq!(dimension(5, 0).upper_bound(), Some(4));
+    assert_eq!(dimension(i32::MIN, 0).upper_bound(), None);
+    assert_eq!(dimension(i32::MAX, 1).upper_bound(), Some(i32::MAX));
+    assert_eq!(dimension(i32::MAX, 2).upper_bound(), None);
+    assert_eq!(dimension(1, u32::MAX).upper_bound(), None);
+}
+
+#[test]
+fn flat_and_multi_index() {
+    // ARRAY [0..1, 3..5, -1..2]
+    let data_type = int_array(vec![dimension(0, 2), dimension(3, 3), dimension(-1, 4)]);
+    assert_eq!(data_type.element_count(), Some(24));
+
+    assert_eq!(data_type.flat_index(&[0, 3, -1]), Some(0));
+    assert_eq!(data_type.flat_index(&[0, 3, 2]), Some(3));
+    assert_eq!(data_type.flat_index(&[0, 4, -1]), Some(4));
+    assert_eq!(data_type.flat_index(&[1, 5, 2]), Some(23));
+    for flat in 0..24 {
+        let index = data_type.multi_index(flat).unwrap();
+        assert_eq!(data_type.flat_index(&index), Some(flat));
+    }
+
+    assert_eq!(data_type.multi_index(24), None);
+    assert_eq!(data_type.flat_index(&[2, 3, -1]), None);
+    assert_eq!(data_type.flat_index(&[0, 2, -1]), None);
+    assert_eq!(data_type.flat_index(&[0, 3, 3]), None);
+    assert_eq!(data_type.flat_index(&[0, 3]), None);
+}
+
+#[test]
+fn index_overflow() {
+    let data_type = int_array(vec![dimension(i32::MAX, 2)]);
+    assert_eq!(data_type.flat_index(&[i32::MAX]), Some(0));
+    assert_eq!(data_type.multi_index(0), Some(vec![i32::MAX]));
+    assert_eq!(data_type.multi_index(1), None);
+
+    let data_type = int_array(vec![dimension(0, 0x8000_0000); 3]);
+    assert_eq!(data_type.multi_index(0), None);
+    assert_eq!(data_type.flat_index(&[0x7FFF_FFFF; 3]), None);
+}
+
+#[test]
+fn decode_overflowing_dimensions() {
+    let symbol_stream = with_dimensions(vec![dimension(0, 0x8000_0000); 3]);
+    assert!(matches!(
+        decode_int_array(&symbol_stream),
+        Err(DecodeError::InvalidArrayBounds { .. })
+    ));
+
+    let message = DataStreamWriter::new(&ema())
+        .write(&[Sample {
+            timestamp: None,
+            values: vec![],
+        }])
+        .unwrap();
+    let mut stream = DataStream::parse(&message).unwrap();
+    stream.header.layout = symbol_stream.header.layout;
+    assert!(matches!(
+        SampleDecoder::new(&symbol_stream).decode(&stream),
+        Err(DecodeError::InvalidArrayBounds { .. })
+    ));
+}
+
+#[test]
+fn decode_more_elements_than_bytes() {
+    let symbol_stream = with_dimensions(vec![dimension(0, 100)]);
+    assert!(matches!(
+        decode_int_array(&symbol_stream),
+        Err(DecodeError::InvalidArrayBounds { .. })
+    ));
+
+    let symbol_stream = with_dimensions(vec![dimension(0, 2), dimension(0, 4)]);
+    assert!(decode_int_array(&symbol_stream).is_ok());
+}
+
+#[test]
+fn decode_elements_not_filling_bytes() {
+    // Three elements in 16 bytes, and no elements in 16 bytes
+    for (count, expected) in [(3, 15), (0, 0)] {
+        let symbol_stream = with_dimensions(vec![dimension(0, count)]);
+        assert_eq!(
+            decode_int_array(&symbol_stream),
+            Err(DecodeError::LengthMismatch {
+                offset: 0,
+                expected,
+                actual: 16,
+            })
+        );
+    }
+}
+
+#[test]
+fn encode_elements_not_filling_bytes() {
+    for count in [3, 0] {
+        let symbol_stream = with_dimensions(vec![dimension(0, count)]);
+        let sample = Sample {
+            timestamp: None,
+            values: vec![NamedValue {
+                name: Arc::from("Main.p_intArrayValue"),
+                value: PlcValue::Array(vec![PlcValue::Int(1); count as usize]),
+            }],
+        };
+        assert!(matches!(
+            DataStreamWriter::new(&symbol_stream).write(&[sample]),
+            Err(EncodeError::InvalidValue { .. })
+        ));
+    }
+}
@@ -0,0 +1,78 @@
+//! Decodes the attribute pragmas of symbols and data types.
+
+mod common;
+
+use binary_decoder::{Attribute, Attributes, SymbolFlags, SymbolStream, SymbolStreamRef};
+
+use common::ema;
+
+fn attribute(key: &str, value: &str) -> Attribute {
+    Attribute {
+        key: key.to_string(),
+        value: value.to_string(),
+    }
+}
+
+/// The ema stream with `{attribute 'analytics_unit' := 'bar'}` and a flag attribute on its
+/// first symbol, as received from the wire.
+fn tagged() -> Vec<u8> {
+    let mut symbol_stream = ema();
+    let symbol = &mut symbol_stream.symbols[0];
+    symbol.flags.insert(SymbolFlags::ATTRIBUTES);
+    symbol.attributes = Attributes(vec![
+        attribute("analytics_unit", "bar"),
+        attribute("hide", ""),
+    ]);
+    symbol_stream.encode()
+}
+
+#[test]
+fn data_type_attributes() {
+    let symbol_stream = ema();
+    let int = &symbol_stream.data_type("INT").unwrap().attributes;
+    assert_eq!(int.len(), 2);
+    assert_eq!(
+        int.iter().map(|a| a.key.as_str()).collect::<Vec<_>>(),
+        ["DisplayMinValue", "DisplayMaxValue"]
+    );
+    assert_eq!(int.get("DisplayMinValue"), Some("#x8000"));
+    assert_eq!(int.get("displaymaxvalue"), Some("#x7fff"));
+    assert_eq!(int.get("analytics_unit"), None);
+
+    // Attributes without a value
+    let test_enum = &symbol_stream.data_type("TestEnum").unwrap().attributes;
+    assert!(test_enum.contains("qualified_only"));
+    assert_eq!(test_enum.get("qualified_only"), Some(""));
+
+    let test_struct = &symbol_stream.data_type("TestStruct").unwrap().attributes;
+    assert!(test_struct.is_empty());
+}
+
+#[test]
+fn symbol_attributes() {
+    let data = tagged();
+    let symbol_stream = SymbolStream::parse(&data).unwrap();
+    let attributes = &symbol_stream.symbols[0].attributes;
+    assert_eq!(
+        *attributes,
+        Attributes(vec![
+            attribute("analytics_unit", "bar"),
+            attribute("hide", ""),
+        ])
+    );
+    assert_eq!(attributes.get("Analytics_Unit"), Some("bar"));
+    assert!(attributes.contains("hide"));
+
+    // The fields after the attributes are still aligned
+    assert_eq!(symbol_stream.symbols[1].name, "Main.b_sintValue");
+    assert!(symbol_stream.symbols[1].attributes.is_empty());
+
+    let borrowed = SymbolStreamRef::parse(&data).unwrap();
+    let symbol = borrowed.symbols().next().unwrap().unwrap();
+    assert_eq!(symbol.attributes.len(), 2);
+    assert_eq!(
+        symbol.attributes.get("analytics_unit").as_deref(),
+        Some("bar")
+    );
+    assert_eq!(symbol.attributes.into_owned(), *attributes);
+}
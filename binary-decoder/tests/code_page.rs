@@ -0,0 +1,134 @@
+//! Decodes and encodes text in the code page named by the symbol stream header.
+
+mod common;
+
+use std::sync::Arc;
+
+use binary_decoder::{
+    CodePage, DataStream, DataStreamWriter, DecodeError, EncodeError, NamedValue, PlcValue,
+    Sample, SampleDecoder, SymbolStream, SymbolStreamRef, TextMode,
+};
+
+use common::{ema, CODE_PAGE_OFFSET};
+
+const COMMENT: &str = "Außentemperatur in °C – gemittelt";
+
+/// The `ema.symbol_stream` layout as an older project in Windows-1252 would publish it.
+fn windows_1252() -> SymbolStream {
+    let mut symbol_stream = ema();
+    symbol_stream.header.code_page = CodePage::WINDOWS_1252.0;
+    symbol_stream.symbols[0].comment = COMMENT.to_string();
+    symbol_stream
+}
+
+fn set_code_page(data: &mut [u8], code_page: u32) {
+    data[CODE_PAGE_OFFSET..CODE_PAGE_OFFSET + 4].copy_from_slice(&code_page.to_le_bytes());
+}
+
+fn string_sample(text: &str) -> Sample {
+    Sample {
+        timestamp: None,
+        values: vec![NamedValue {
+            name: Arc::from("Main.l_stringValue"),
+            value: PlcValue::String(text.into()),
+        }],
+    }
+}
+
+#[test]
+fn names_and_comments() {
+    let data = windows_1252().encode();
+    // One byte per character, e.g. 0xB0 for the degree sign
+    assert!(data.windows(4).any(|w| w == b"\xb0C \x96"));
+
+    let symbol_stream = SymbolStream::parse(&data).unwrap();
+    assert_eq!(symbol_stream.code_page(), CodePage::WINDOWS_1252);
+    assert_eq!(symbol_stream.symbols[0].comment, COMMENT);
+    assert_eq!(symbol_stream.encode(), data);
+
+    let borrowed = SymbolStreamRef::parse(&data).unwrap();
+    let symbol = borrowed.symbols().next().unwrap().unwrap();
+    assert_eq!(symbol.comment, COMMENT);
+    assert_eq!(symbol.name, "Main.a_boolValue");
+}
+
+#[test]
+fn strict_and_lossy() {
+    let mut data = windows_1252().encode();
+    // Windows-1252 text is not valid UTF-8
+    set_code_page(&mut data, CodePage::UTF_8.0);
+
+    assert!(matches!(
+        SymbolStream::parse(&data),
+        Err(DecodeError::InvalidString { .. })
+    ));
+    let lossy = SymbolStream::parse_with(&data, TextMode::Lossy).unwrap();
+    assert_eq!(
+        lossy.symbols[0].comment,
+        "Au\u{fffd}entemperatur in \u{fffd}C \u{fffd} gemittelt"
+    );
+
+    set_code_page(&mut data, 12345);
+    assert_eq!(
+        SymbolStream::parse(&data).unwrap_err(),
+        DecodeError::UnsupportedCodePage { code_page: 12345 }
+    );
+    let lossy = SymbolStream::parse_with(&data, TextMode::Lossy).unwrap();
+    assert_eq!(lossy.symbols.len(), 40);
+}
+
+#[test]
+fn string_values() {
+    let symbol_stream = windows_1252();
+    let writer = DataStreamWriter::new(&symbol_stream);
+    let decoder = SampleDecoder::new(&symbol_stream);
+
+    let message = writer.write(&[string_sample("Grüße – 5 €")]).unwrap();
+    let stream = DataStream::parse(&message).unwrap();
+    let symbol = &symbol_stream.symbols[11];
+    assert_eq!(symbol.name, "Main.l_stringValue");
+    let (offset, _) = symbol.sample_location();
+    assert_eq!(
+        &stream.get(0).unwrap().data[offset..offset + 12],
+        b"Gr\xfc\xdfe \x96 5 \x80\0"
+    );
+
+    let decoded = decoder.decode(&stream).unwrap();
+    assert_eq!(
+        decoded[0].get("Main.l_stringValue"),
+        Some(&PlcValue::String("Grüße – 5 €".into()))
+    );
+
+    assert_eq!(
+        writer.write(&[string_sample("x ∑ y")]),
+        Err(EncodeError::InvalidValue {
+            symbol: "Main.l_stringValue".into(),
+            value: "'x ∑ y'".into(),
+        })
+    );
+}
+
+#[test]
+fn strict_string_values() {
+    let symbol_stream = ema();
+    let mut message = DataStreamWriter::new(&symbol_stream)
+        .write(&[string_sample("abc")])
+        .unwrap();
+    let header = DataStream::parse(&message).unwrap().header;
+    let (offset, _) = symbol_stream.symbols[11].sample_location();
+    let data_start = header.header_len as usize + header.sample_header_len as usize;
+    message[data_start + offset + 1] = 0xff;
+    let stream = DataStream::parse(&message).unwrap();
+
+    let lossy = SampleDecoder::new(&symbol_stream).decode(&stream).unwrap();
+    assert_eq!(
+        lossy[0].get("Main.l_stringValue"),
+        Some(&PlcValue::String("a\u{fffd}c".into()))
+    );
+
+    let strict = SampleDecoder::new(&symbol_stream).with_text_mode(TextMode::Strict);
+    assert_eq!(
+        strict.decode(&stream).unwrap_err(),
+        DecodeError::InvalidString { offset }
+    );
+}
@@ -0,0 +1,179 @@
+//! Fixtures shared by the integration tests. Each test uses only some of them.
+
+#![allow(dead_code)]
+
+use binary_decoder::{AdsDataType, DataStreamFlags, DataType, DataTypeFlags, Symbol, SymbolStream};
+
+/// A symbol stream published by TwinCAT 3, with a symbol of most kinds of type.
+pub const EMA: &[u8] = include_bytes!("../../ema.symbol_stream");
+
+/// Length of the symbol stream header, where the first symbol record starts.
+pub const HEADER_LEN: usize = 64;
+
+/// Offset of the data type section size in the symbol stream header.
+pub const DATA_TYPE_DATA_LEN_OFFSET: usize = 16;
+
+/// Offset of the code page in the symbol stream header.
+pub const CODE_PAGE_OFFSET: usize = 24;
+
+/// Offset of the layout GUID in the symbol stream header.
+pub const LAYOUT_OFFSET: usize = 48;
+
+/// The layout GUID of `EMA` as it is laid out in headers, mixed-endian.
+pub const LAYOUT: [u8; 16] = [
+    0xa2, 0xfb, 0x79, 0x46, 0xaf, 0xbe, 0x6a, 0xff, 0x33, 0x8f, 0x45, 0x1f, 0x83, 0x72, 0x61, 0xf8,
+];
+
+/// Time between two samples of the data streams built here, in 100 ns units.
+pub const CYCLE_TIME: u32 = 10_000;
+
+pub fn ema() -> SymbolStream {
+    SymbolStream::parse(EMA).unwrap()
+}
+
+/// The symbol called `name`.
+pub fn symbol<'a>(symbol_stream: &'a SymbolStream, name: &str) -> &'a Symbol {
+    symbol_stream
+        .symbols
+        .iter()
+        .find(|symbol| symbol.name == name)
+        .unwrap()
+}
+
+/// The data type called `name`, to be edited.
+pub fn data_type_mut<'a>(symbol_stream: &'a mut SymbolStream, name: &str) -> &'a mut DataType {
+    symbol_stream
+        .data_types
+        .iter_mut()
+        .find(|data_type| data_type.name == name)
+        .unwrap()
+}
+
+/// A 1.0 data stream header with the layout of `EMA`, for samples of `sample_data_len` bytes.
+/// Samples carry a timestamp of their own if `flags` says so.
+pub fn header_1_0(sample_data_len: u32, flags: DataStreamFlags) -> Vec<u8> {
+    let sample_header_len = if flags.contains(DataStreamFlags::SAMPLE_TIMESTAMP) {
+        8
+    } else {
+        0
+    };
+    let mut header = vec![1, 0, 32, sample_header_len];
+    header.extend(sample_data_len.to_le_bytes());
+    header.extend(CYCLE_TIME.to_le_bytes());
+    header.extend(flags.bits().to_le_bytes());
+    header.extend(LAYOUT);
+    header
+}
+
+/// Like [`header_1_0`], a 1.1 header for `count` samples, the first at `start` and one cycle
+/// apart.
+pub fn header_1_1(sample_data_len: u32, flags: DataStreamFlags, count: u64, start: u64) -> Vec<u8> {
+    let mut header = header_1_0(sample_data_len, flags);
+    header[1] = 1;
+    header[2] = 56;
+    header.extend(count.to_le_bytes());
+    header.extend(start.to_le_bytes());
+    let stop = start.saturating_add(
+        count
+            .saturating_sub(1)
+            .saturating_mul(u64::from(CYCLE_TIME)),
+    );
+    header.extend(stop.to_le_bytes());
+    header
+}
+
+/// `body` with the length prefix of a record, which counts itself.
+pub fn record(body: Vec<u8>) -> Vec<u8> {
+    let mut record = (body.len() as u32 + 4).to_le_bytes().to_vec();
+    record.extend(body);
+    record
+}
+
+/// The lengths of `texts` without their terminator, then `counts`, then the NUL terminated
+/// texts, as names and comments are laid out in every record.
+pub fn texts(out: &mut Vec<u8>, counts: &[u16], texts: &[&str]) {
+    for text in texts {
+        out.extend((text.len() as u16).to_le_bytes());
+    }
+    for &value in counts {
+        out.extend(value.to_le_bytes());
+    }
+    for text in texts {
+        out.extend(text.as_bytes());
+        out.push(0);
+    }
+}
+
+pub fn u32s(out: &mut Vec<u8>, values: &[u32]) {
+    for value in values {
+        out.extend(value.to_le_bytes());
+    }
+}
+
+/// The body of a data type record up to its sub items: the fixed fields, then `name` and two
+/// empty strings for its type name and comment.
+pub fn data_type_body(name: &str, len: u32, flags: DataTypeFlags, sub_item_count: u16) -> Vec<u8> {
+    let mut body = vec![];
+    // Version, hash, type hash, size, offset, base type, flags
+    u32s(
+        &mut body,
+        &[1, 0, 0, len, 0, AdsDataType::BigType as u32, flags.bits()],
+    );
+    // No array dimensions
+    texts(&mut body, &[0, sub_item_count], &[name, "", ""]);
+    body
+}
+
+/// A data type record without sub items, with `tail` after its texts.
+pub fn data_type(name: &str, len: u32, flags: DataTypeFlags, tail: &[u8]) -> Vec<u8> {
+    let mut body = data_type_body(name, len, flags, 0);
+    body.extend(tail);
+    record(body)
+}
+
+/// A UTF-8 symbol stream without symbols, with `count` data types in `data_types`.
+pub fn symbol_stream(count: u32, data_types: &[u8]) -> Vec<u8> {
+    let mut stream = vec![3, 0];
+    stream.extend((HEADER_LEN as u16).to_le_bytes());
+    // Symbol count and size, data type count and size, dynamic symbols, code page, flags
+    u32s(
+        &mut stream,
+        &[0, 0, count, data_types.len() as u32, 0, 65001, 0],
+    );
+    stream.extend([0; 32]);
+    stream.extend(data_types);
+    stream
+}
+
+/// A stream whose only data type `T0` has a member `T1`, which has a member `T2` of its own,
+/// and so on `depth` records deep.
+pub fn nested(depth: usize) -> Vec<u8> {
+    // Built from the outside in, as copying ever longer records would take long
+    let bodies: Vec<Vec<u8>> = (0..=depth)
+        .map(|level| {
+            let sub_item_count = u16::from(level < depth);
+            data_type_body(
+                &format!("T{level}"),
+                1,
+                DataTypeFlags::DATA_TYPE,
+                sub_item_count,
+            )
+        })
+        .collect();
+    let mut lens: Vec<u32> = bodies
+        .iter()
+        .rev()
+        .scan(0, |len, body| {
+            *len += body.len() as u32 + 4;
+            Some(*len)
+        })
+        .collect();
+    lens.reverse();
+
+    let mut data_types = vec![];
+    for (body, len) in bodies.into_iter().zip(lens) {
+        data_types.extend(len.to_le_bytes());
+        data_types.extend(body);
+    }
+    symbol_stream(1, &data_types)
+}
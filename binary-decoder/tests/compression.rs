@@ -0,0 +1,179 @@
+//! Round trips sample payloads through the data stream compression methods.
+
+mod common;
+
+use binary_decoder::{Compression, DataStream, DataStreamFlags, DecodeError};
+
+use common::{header_1_0, header_1_1};
+
+/// A 1.0 data stream header for samples of `sample_data_len` bytes compressed with
+/// `compression_method`.
+fn header(sample_data_len: u32, compression_method: u32) -> Vec<u8> {
+    header_1_0(
+        sample_data_len,
+        DataStreamFlags::from(compression_method << 4),
+    )
+}
+
+fn payloads() -> Vec<Vec<u8>> {
+    vec![
+        vec![],
+        vec![7],
+        vec![0; 1000],
+        (0..=255).collect(),
+        (0..1000).map(|i| (i / 5) as u8).collect(),
+        [vec![1, 2], vec![3; 3], vec![4; 2], vec![5; 129], vec![6]].concat(),
+    ]
+}
+
+#[test]
+fn run_length_round_trip() {
+    for payload in payloads() {
+        let compressed = Compression::RunLength.compress(&payload);
+        let restored = Compression::RunLength
+            .decompress(&compressed, payload.len())
+            .unwrap();
+        assert_eq!(restored, payload);
+    }
+}
+
+#[test]
+fn run_length_shrinks_repeated_bytes() {
+    let compressed = Compression::RunLength.compress(&[0; 1000]);
+    assert_eq!(compressed.len(), 16);
+}
+
+#[test]
+fn run_length_known_encoding() {
+    let encoded = [2, b'a', b'b', b'c', 0xFE, b'x', 128, 0, b'y'];
+    let decoded = Compression::RunLength.decompress(&encoded, 7).unwrap();
+    assert_eq!(decoded, b"abcxxxy");
+}
+
+#[test]
+fn run_length_truncated() {
+    let err = Compression::RunLength
+        .decompress(&[4, 1, 2], 8)
+        .unwrap_err();
+    assert_eq!(
+        err,
+        DecodeError::Truncated {
+            offset: 1,
+            needed: 3
+        }
+    );
+
+    let err = Compression::RunLength.decompress(&[0xFE], 8).unwrap_err();
+    assert_eq!(
+        err,
+        DecodeError::Truncated {
+            offset: 1,
+            needed: 1
+        }
+    );
+}
+
+#[test]
+fn run_length_limit() {
+    // Each pair of bytes expands to 128
+    let encoded = [0x81, 0].repeat(3);
+    assert_eq!(
+        Compression::RunLength.decompress(&encoded, 384),
+        Ok(vec![0; 384])
+    );
+    assert_eq!(
+        Compression::RunLength
+            .decompress(&encoded, 300)
+            .unwrap_err(),
+        DecodeError::LengthMismatch {
+            offset: 4,
+            expected: 300,
+            actual: 384
+        }
+    );
+
+    let err = Compression::RunLength
+        .decompress(&[2, 1, 2, 3], 2)
+        .unwrap_err();
+    assert_eq!(
+        err,
+        DecodeError::LengthMismatch {
+            offset: 0,
+            expected: 2,
+            actual: 3
+        }
+    );
+}
+
+#[test]
+fn compressed_data_stream() {
+    let samples: Vec<u8> = (0..40).map(|i| if i % 8 < 6 { 0 } else { i }).collect();
+
+    let mut message = header(8, 1);
+    message.extend(Compression::RunLength.compress(&samples));
+    let stream = DataStream::parse(&message).unwrap();
+
+    assert_eq!(
+        stream.header.flags.compression(),
+        Ok(Compression::RunLength)
+    );
+    assert_eq!(stream.len(), 5);
+    for (sample, expected) in stream.samples().zip(samples.chunks(8)) {
+        assert_eq!(&sample.data[..], expected);
+    }
+}
+
+#[test]
+fn compressed_data_stream_limit() {
+    let flags = DataStreamFlags::from(1 << 4);
+    let payload = [0x81, 0].repeat(1000);
+
+    // The header of a 1.1 stream says how large its payload is
+    let mut message = header_1_1(8, flags, 2, 0);
+    message.extend(&payload);
+    assert_eq!(
+        DataStream::parse(&message).unwrap_err(),
+        DecodeError::LengthMismatch {
+            offset: 56,
+            expected: 16,
+            actual: 128
+        }
+    );
+
+    // A 1.0 stream is bounded by the limit only
+    let mut message = header(8, 1);
+    message.extend(&payload);
+    assert_eq!(DataStream::parse(&message).unwrap().len(), 16_000);
+    assert_eq!(
+        DataStream::parse_with_limit(&message, 1000).unwrap_err(),
+        DecodeError::LengthMismatch {
+            offset: 46,
+            expected: 1000,
+            actual: 1024
+        }
+    );
+}
+
+#[test]
+fn truncated_compressed_data_stream() {
+    let mut message = header(8, 1);
+    message.extend([10, 1, 2]);
+
+    let err = DataStream::parse(&message).unwrap_err();
+    assert_eq!(
+        err,
+        DecodeError::Truncated {
+            offset: 33,
+            needed: 9
+        }
+    );
+}
+
+#[test]
+fn unknown_compression_method() {
+    let mut message = header(8, 2);
+    message.extend([0; 8]);
+
+    let err = DataStream::parse(&message).unwrap_err();
+    assert_eq!(err, DecodeError::UnsupportedCompression { method: 2 });
+}
@@ -0,0 +1,126 @@
+//! Round trips the copy mask of a data type and rejects masks that do not fit the type.
+
+mod common;
+
+use std::ops::Range;
+
+use binary_decoder::{CopyMask, DataTypeFlags, DecodeError, SymbolStream};
+
+use common::{data_type_mut, ema, DATA_TYPE_DATA_LEN_OFFSET};
+
+/// Significant bits of `TestStruct`: four whole members, six bit members and a padding byte.
+const MASK: [u8; 6] = [0xFF, 0xFF, 0xFF, 0xFF, 0x3F, 0x00];
+
+/// Offset of the name within a data type record.
+const DATA_TYPE_NAME_OFFSET: usize = 42;
+
+/// The ema stream with a copy mask for `TestStruct`, encoded.
+fn with_mask(mask: &[u8]) -> Vec<u8> {
+    let mut symbol_stream = ema();
+    let test_struct = data_type_mut(&mut symbol_stream, "TestStruct");
+    test_struct.flags.insert(DataTypeFlags::COPY_MASK);
+    test_struct.copy_mask = Some(CopyMask(mask.to_vec()));
+    symbol_stream.encode()
+}
+
+fn read_u32(data: &[u8], offset: usize) -> u32 {
+    u32::from_le_bytes(data[offset..offset + 4].try_into().unwrap())
+}
+
+/// Where the top level data type record called `name` is in `data`.
+fn data_type_record(data: &[u8], name: &str) -> Range<usize> {
+    let header_len = u16::from_le_bytes([data[2], data[3]]) as usize;
+    let mut start = header_len + read_u32(data, 8) as usize;
+    loop {
+        let end = start + read_u32(data, start) as usize;
+        let name_start = start + DATA_TYPE_NAME_OFFSET;
+        if data[name_start..].starts_with(name.as_bytes()) && data[name_start + name.len()] == 0 {
+            return start..end;
+        }
+        start = end;
+    }
+}
+
+/// Where the copy mask ends within `record`, ahead of the padding of the record.
+fn mask_end(data: &[u8], record: Range<usize>) -> usize {
+    let start = data[record.clone()]
+        .windows(MASK.len())
+        .rposition(|window| window == MASK)
+        .unwrap();
+    record.start + start + MASK.len()
+}
+
+fn test_struct_mask(data: &[u8]) -> Option<CopyMask> {
+    let symbol_stream = SymbolStream::parse(data).unwrap();
+    symbol_stream
+        .data_type("TestStruct")
+        .unwrap()
+        .copy_mask
+        .clone()
+}
+
+#[test]
+fn round_trip() {
+    let data = with_mask(&MASK);
+    let record = data_type_record(&data, "TestStruct");
+    // The mask is the last field of the record, followed only by its padding
+    let padding = mask_end(&data, record.clone())..record.end;
+    assert!(data[padding].iter().all(|&byte| byte == 0));
+    assert_eq!(test_struct_mask(&data), Some(CopyMask(MASK.to_vec())));
+
+    assert_eq!(ema().data_type("TestStruct").unwrap().copy_mask, None);
+}
+
+#[test]
+fn mask_fitted_to_type() {
+    // The encoder writes exactly as many mask bytes as the type has
+    let data = with_mask(&MASK[..4]);
+    assert_eq!(
+        test_struct_mask(&data),
+        Some(CopyMask(vec![0xFF, 0xFF, 0xFF, 0xFF, 0x00, 0x00]))
+    );
+
+    let data = with_mask(&[0xFF; 10]);
+    assert_eq!(test_struct_mask(&data), Some(CopyMask(vec![0xFF; 6])));
+}
+
+#[test]
+fn mask_shorter_than_type() {
+    let mut data = with_mask(&MASK);
+    let record = data_type_record(&data, "TestStruct");
+
+    // End the record one byte into the mask, and shrink the data type section to match
+    let cut = mask_end(&data, record.clone()) - 1;
+    let removed = (record.end - cut) as u32;
+    data.drain(cut..record.end);
+    let record_len = read_u32(&data, record.start) - removed;
+    data[record.start..record.start + 4].copy_from_slice(&record_len.to_le_bytes());
+    let section_len = read_u32(&data, DATA_TYPE_DATA_LEN_OFFSET) - removed;
+    data[DATA_TYPE_DATA_LEN_OFFSET..DATA_TYPE_DATA_LEN_OFFSET + 4]
+        .copy_from_slice(&section_len.to_le_bytes());
+
+    assert!(matches!(
+        SymbolStream::parse(&data),
+        Err(DecodeError::Truncated { needed: 1, .. })
+    ));
+}
+
+#[test]
+fn significant_bits() {
+    let mask = CopyMask(MASK.to_vec());
+    assert!(mask.is_significant(0));
+    assert!(mask.is_significant(4));
+    assert!(!mask.is_significant(5));
+    assert!(!mask.is_significant(6));
+
+    let value = [1, 2, 3, 4, 0xFF, 0xAA];
+    assert_eq!(mask.apply(&value), [1, 2, 3, 4, 0x3F, 0]);
+    assert_eq!(
+        mask.significant_bytes(&value).collect::<Vec<_>>(),
+        [(0, 1), (1, 2), (2, 3), (3, 4), (4, 0x3F)]
+    );
+
+    // Padding and unused bits do not count as a change
+    assert!(!mask.differs(&value, &[1, 2, 3, 4, 0x7F, 0x55]));
+    assert!(mask.differs(&value, &[1, 2, 3, 5, 0xFF, 0xAA]));
+}
@@ -0,0 +1,195 @@
+//! Parses hand-built data stream messages with 1.0 and 1.1 headers.
+
+mod common;
+
+use binary_decoder::{DataStream, DataStreamFlags, DecodeError, Timestamp};
+use uuid::uuid;
+
+use common::header_1_0;
+
+const START: u64 = 133_000_000_000_000_000;
+
+/// A 1.1 header for `count` samples of four bytes, the first at `START` and one cycle apart.
+fn header_1_1(count: u64, flags: DataStreamFlags) -> Vec<u8> {
+    common::header_1_1(4, flags, count, START)
+}
+
+#[test]
+fn version_1_0() {
+    let mut message = header_1_0(4, DataStreamFlags::SAMPLE_TIMESTAMP);
+    for (time, data) in [(START, [1, 2, 3, 4]), (START + 7, [5, 6, 7, 8])] {
+        message.extend(time.to_le_bytes());
+        message.extend(data);
+    }
+    let stream = DataStream::parse(&message).unwrap();
+
+    let header = &stream.header;
+    assert_eq!(header.version.to_string(), "1.0");
+    assert_eq!(header.header_len, 32);
+    assert_eq!(header.sample_header_len, 8);
+    assert_eq!(header.sample_data_len, 4);
+    assert_eq!(header.sample_len(), 12);
+    assert_eq!(header.cycle_time, 10_000);
+    assert_eq!(header.layout, uuid!("4679fba2-beaf-ff6a-338f-451f837261f8"));
+    assert_eq!(header.sample_count, None);
+    assert_eq!(header.start(), None);
+    assert_eq!(header.stop(), None);
+
+    // The sample count follows from the payload size
+    let samples: Vec<_> = stream.samples().collect();
+    assert_eq!(samples.len(), 2);
+    assert_eq!(samples[0].timestamp, Some(Timestamp::FileTime(START)));
+    assert_eq!(&samples[0].data[..], [1, 2, 3, 4]);
+    assert_eq!(samples[1].timestamp, Some(Timestamp::FileTime(START + 7)));
+    assert_eq!(&samples[1].data[..], [5, 6, 7, 8]);
+}
+
+#[test]
+fn version_1_0_without_timestamps() {
+    let mut message = header_1_0(4, DataStreamFlags::empty());
+    message.extend([0; 12]);
+    let stream = DataStream::parse(&message).unwrap();
+
+    assert_eq!(stream.len(), 3);
+    assert!(stream.samples().all(|s| s.timestamp.is_none()));
+}
+
+#[test]
+fn version_1_1() {
+    let mut message = header_1_1(3, DataStreamFlags::HEAD_TIMESTAMP);
+    message.extend((0..12).collect::<Vec<u8>>());
+    let stream = DataStream::parse(&message).unwrap();
+
+    let header = &stream.header;
+    assert_eq!(header.version.to_string(), "1.1");
+    assert_eq!(header.header_len, 56);
+    assert_eq!(header.sample_count, Some(3));
+    assert_eq!(header.start(), Some(Timestamp::FileTime(START)));
+    assert_eq!(header.stop(), Some(Timestamp::FileTime(START + 20_000)));
+
+    // Samples without a timestamp of their own are counted on from the start time
+    let timestamps: Vec<_> = stream.samples().map(|s| s.timestamp).collect();
+    assert_eq!(
+        timestamps,
+        [
+            Some(Timestamp::FileTime(START)),
+            Some(Timestamp::FileTime(START + 10_000)),
+            Some(Timestamp::FileTime(START + 20_000)),
+        ]
+    );
+    assert_eq!(&stream.get(2).unwrap().data[..], [8, 9, 10, 11]);
+    assert!(stream.get(3).is_none());
+
+    // Samples are split off the payload in either direction
+    let last = stream.samples().next_back().unwrap();
+    assert_eq!(last.timestamp, Some(Timestamp::FileTime(START + 20_000)));
+    assert_eq!(stream.samples().len(), 3);
+}
+
+#[test]
+fn version_1_1_dc_time() {
+    let mut message = header_1_1(
+        1,
+        DataStreamFlags::HEAD_TIMESTAMP | DataStreamFlags::DC_TIME,
+    );
+    message.extend([0; 4]);
+    let stream = DataStream::parse(&message).unwrap();
+
+    assert_eq!(stream.header.start(), Some(Timestamp::DcTime(START)));
+    assert_eq!(
+        stream.get(0).unwrap().timestamp,
+        Some(Timestamp::DcTime(START))
+    );
+}
+
+#[test]
+fn version_1_1_without_head_timestamp() {
+    let mut message = header_1_1(2, DataStreamFlags::empty());
+    message.extend([0; 8]);
+    let stream = DataStream::parse(&message).unwrap();
+
+    // The times are in the header, but the flag says they are not valid
+    assert_eq!(stream.header.start_time, Some(START));
+    assert_eq!(stream.header.start(), None);
+    assert!(stream.samples().all(|s| s.timestamp.is_none()));
+}
+
+#[test]
+fn sample_count_mismatch() {
+    let mut message = header_1_1(3, DataStreamFlags::HEAD_TIMESTAMP);
+    message.extend([0; 8]);
+    assert_eq!(
+        DataStream::parse(&message).unwrap_err(),
+        DecodeError::LengthMismatch {
+            offset: 56,
+            expected: 12,
+            actual: 8,
+        }
+    );
+
+    // A count that no payload could hold
+    let mut message = header_1_1(u64::MAX, DataStreamFlags::HEAD_TIMESTAMP);
+    message.extend([0; 8]);
+    assert_eq!(
+        DataStream::parse(&message).unwrap_err(),
+        DecodeError::LengthMismatch {
+            offset: 56,
+            expected: usize::MAX,
+            actual: 8,
+        }
+    );
+}
+
+#[test]
+fn short_sample_header() {
+    // Samples are flagged to carry a timestamp, but their header has no room for one
+    let mut message = header_1_0(4, DataStreamFlags::SAMPLE_TIMESTAMP);
+    message[3] = 4;
+    message.extend([0; 16]);
+    assert_eq!(
+        DataStream::parse(&message).unwrap_err(),
+        DecodeError::LengthMismatch {
+            offset: 3,
+            expected: 8,
+            actual: 4,
+        }
+    );
+}
+
+#[test]
+fn longer_header() {
+    // A later revision may append to the header, which is skipped
+    let mut message = header_1_1(1, DataStreamFlags::HEAD_TIMESTAMP);
+    message[2] = 60;
+    message.extend([0xEE; 4]);
+    message.extend([1, 2, 3, 4]);
+    let stream = DataStream::parse(&message).unwrap();
+    assert_eq!(&stream.get(0).unwrap().data[..], [1, 2, 3, 4]);
+}
+
+#[test]
+fn unsupported_versions() {
+    for (major, minor) in [(0, 9), (1, 2), (2, 0)] {
+        let mut message = header_1_0(4, DataStreamFlags::empty());
+        message[0] = major;
+        message[1] = minor;
+        assert_eq!(
+            DataStream::parse(&message).unwrap_err(),
+            DecodeError::UnsupportedVersion { major, minor }
+        );
+    }
+}
+
+#[test]
+fn truncated_header() {
+    let message = header_1_1(1, DataStreamFlags::HEAD_TIMESTAMP);
+    for len in [0, 1, 4, 31, 40, 55] {
+        assert!(
+            matches!(
+                DataStream::parse(&message[..len]),
+                Err(DecodeError::Truncated { .. })
+            ),
+            "cut at {len}"
+        );
+    }
+}
@@ -0,0 +1,298 @@
+//! Writes data streams for the `ema.symbol_stream` layout and decodes them again.
+
+mod common;
+
+use std::sync::Arc;
+
+use binary_decoder::{
+    Compression, DataStream, DataStreamFlags, DataStreamWriter, EncodeError, NamedValue, PlcValue,
+    Sample, SampleDecoder, Timestamp, Version,
+};
+
+use common::ema;
+
+fn sample(timestamp: Option<Timestamp>, values: Vec<(&str, PlcValue)>) -> Sample {
+    let values = values
+        .into_iter()
+        .map(|(name, value)| NamedValue {
+            name: Arc::from(format!("Main.{name}")),
+            value,
+        })
+        .collect();
+    Sample { timestamp, values }
+}
+
+fn test_struct(seed: i16) -> PlcValue {
+    PlcValue::Struct(vec![
+        ("a_boolMember".into(), PlcValue::Bool(true)),
+        ("b_sintMember".into(), PlcValue::SInt(-3)),
+        ("c_intMember".into(), PlcValue::Int(seed)),
+        ("d_bit0Member".into(), PlcValue::Bool(true)),
+        ("d_bit1Member".into(), PlcValue::Bool(false)),
+        ("d_bit2Member".into(), PlcValue::Bool(false)),
+        ("d_bit3Member".into(), PlcValue::Bool(true)),
+        ("d_bit4Member".into(), PlcValue::Bool(false)),
+        ("d_bit5Member".into(), PlcValue::Bool(true)),
+    ])
+}
+
+fn samples() -> Vec<Sample> {
+    (0..4i8)
+        .map(|i| {
+            let timestamp = Timestamp::FileTime(133_000_000_000_000_000 + i as u64 * 10_000);
+            let multi_array = PlcValue::Array(
+                (0..2)
+                    .map(|a| {
+                        PlcValue::Array(
+                            (0..3)
+                                .map(|b| {
+                                    PlcValue::Array(
+                                        (0..4)
+                                            .map(|c| PlcValue::Int(a * 100 + b * 10 + c))
+                                            .collect(),
+                                    )
+                                })
+                                .collect(),
+                        )
+                    })
+                    .collect(),
+            );
+            sample(
+                Some(timestamp),
+                vec![
+                    ("a_boolValue", PlcValue::Bool(i % 2 == 0)),
+                    ("b_sintValue", PlcValue::SInt(-i)),
+                    ("c_usintValue", PlcValue::USInt(200)),
+                    ("d_intValue", PlcValue::Int(-1234)),
+                    ("e_uintValue", PlcValue::UInt(0)),
+                    ("f_dintValue", PlcValue::DInt(i32::MIN)),
+                    ("g_udintValue", PlcValue::UDInt(u32::MAX)),
+                    ("h_lintValue", PlcValue::LInt(-(1 << 40))),
+                    ("i_ulintValue", PlcValue::ULInt(u64::MAX)),
+                    ("j_realValue", PlcValue::Real(1.5)),
+                    ("k_lrealValue", PlcValue::LReal(-2.25e-300)),
+                    ("l_stringValue", PlcValue::String(format!("sample {i}"))),
+                    ("m_wstringValue", PlcValue::WString("wörld ∑".into())),
+                    ("n_timeValue", PlcValue::UDInt(1000)),
+                    (
+                        "o_ltimeValue",
+                        PlcValue::Bytes(vec![1, 2, 3, 4, 5, 6, 7, 8]),
+                    ),
+                    (
+                        "p_intArrayValue",
+                        PlcValue::Array((0..8).map(|v| PlcValue::Int(v * i as i16)).collect()),
+                    ),
+                    ("q_intMultiArrayValue", multi_array),
+                    ("r_structValue", test_struct(i.into())),
+                    (
+                        "s_nestedStructValue",
+                        PlcValue::Struct(vec![
+                            ("a_testStruct".into(), test_struct(-7)),
+                            ("b_intValue".into(), PlcValue::Int(42)),
+                        ]),
+                    ),
+                ],
+            )
+        })
+        .collect()
+}
+
+fn write_and_decode(writer: &DataStreamWriter, samples: &[Sample]) -> (DataStream, Vec<Sample>) {
+    let message = writer.write(samples).unwrap();
+    let stream = DataStream::parse(&message).unwrap();
+    let decoded = SampleDecoder::new(&ema()).decode(&stream).unwrap();
+    (stream, decoded)
+}
+
+#[test]
+fn round_trip() {
+    let samples = samples();
+
+    for version in [
+        Version { major: 1, minor: 0 },
+        Version { major: 1, minor: 1 },
+    ] {
+        for sample_timestamps in [false, true] {
+            for compression in [Compression::None, Compression::RunLength] {
+                let writer = DataStreamWriter::new(&ema())
+                    .with_version(version)
+                    .with_cycle_time(10_000)
+                    .with_sample_timestamps(sample_timestamps)
+                    .with_compression(compression);
+                let (stream, decoded) = write_and_decode(&writer, &samples);
+
+                assert_eq!(stream.header.flags.compression(), Ok(compression));
+                assert_eq!(decoded.len(), samples.len());
+                for (written, read) in samples.iter().zip(&decoded) {
+                    for value in &written.values {
+                        assert_eq!(read.get(&value.name), Some(&value.value), "{}", value.name);
+                    }
+                    // Interpolated timestamps need the start time of a 1.1 header
+                    if sample_timestamps || version.minor == 1 {
+                        assert_eq!(read.timestamp, written.timestamp);
+                    }
+                }
+            }
+        }
+    }
+}
+
+#[test]
+fn enum_and_flattened_members() {
+    let samples = [sample(
+        None,
+        vec![
+            ("t_enumValue", PlcValue::String("C_VALUE".into())),
+            ("r_structValue.c_intMember", PlcValue::Int(77)),
+            ("r_structValue.d_bit4Member", PlcValue::Bool(true)),
+        ],
+    )];
+    let (_, decoded) = write_and_decode(&DataStreamWriter::new(&ema()), &samples);
+    let decoded = &decoded[0];
+
+    assert_eq!(
+        decoded.get("Main.t_enumValue"),
+        Some(&PlcValue::Enum {
+            type_name: "TestEnum".into(),
+            name: Some("C_VALUE".into()),
+            value: 2,
+        })
+    );
+    assert_eq!(
+        decoded.get("Main.r_structValue.c_intMember"),
+        Some(&PlcValue::Int(77))
+    );
+    assert_eq!(
+        decoded.get("Main.r_structValue.d_bit4Member"),
+        Some(&PlcValue::Bool(true))
+    );
+}
+
+#[test]
+fn struct_value_covers_flattened_members() {
+    let samples = [sample(
+        None,
+        vec![
+            ("r_structValue", test_struct(77)),
+            // A value of its own takes precedence over the struct's
+            ("r_structValue.b_sintMember", PlcValue::SInt(5)),
+        ],
+    )];
+    let (_, decoded) = write_and_decode(&DataStreamWriter::new(&ema()), &samples);
+    let decoded = &decoded[0];
+
+    assert_eq!(decoded.get("Main.r_structValue"), Some(&test_struct(77)));
+    assert_eq!(
+        decoded.get("Main.r_structValue.c_intMember"),
+        Some(&PlcValue::Int(77))
+    );
+    assert_eq!(
+        decoded.get("Main.r_structValue.d_bit3Member"),
+        Some(&PlcValue::Bool(true))
+    );
+    assert_eq!(
+        decoded.get("Main.r_structValue.b_sintMember"),
+        Some(&PlcValue::SInt(5))
+    );
+
+    // Members the struct value leaves out stay zero
+    let samples = [sample(
+        None,
+        vec![(
+            "r_structValue",
+            PlcValue::Struct(vec![("c_intMember".into(), PlcValue::Int(77))]),
+        )],
+    )];
+    let (_, decoded) = write_and_decode(&DataStreamWriter::new(&ema()), &samples);
+    assert_eq!(
+        decoded[0].get("Main.r_structValue.c_intMember"),
+        Some(&PlcValue::Int(77))
+    );
+    assert_eq!(
+        decoded[0].get("Main.r_structValue.b_sintMember"),
+        Some(&PlcValue::SInt(0))
+    );
+}
+
+#[test]
+fn dc_time() {
+    let samples = samples();
+    let writer = DataStreamWriter::new(&ema())
+        .with_sample_timestamps(true)
+        .with_dc_time(true);
+    let (stream, decoded) = write_and_decode(&writer, &samples);
+
+    assert!(stream.header.flags.contains(DataStreamFlags::DC_TIME));
+    for (written, read) in samples.iter().zip(&decoded) {
+        let read = read.timestamp.unwrap();
+        assert!(matches!(read, Timestamp::DcTime(_)));
+        assert_eq!(
+            read.to_system_time(),
+            written.timestamp.unwrap().to_system_time()
+        );
+    }
+}
+
+#[test]
+fn errors() {
+    let writer = DataStreamWriter::new(&ema());
+
+    let out_of_range = [sample(None, vec![("d_intValue", PlcValue::DInt(70_000))])];
+    assert_eq!(
+        writer.write(&out_of_range),
+        Err(EncodeError::InvalidValue {
+            symbol: "Main.d_intValue".into(),
+            value: "DINT#70000".into(),
+        })
+    );
+
+    let wrong_type = [sample(
+        None,
+        vec![("j_realValue", PlcValue::String("1.5".into()))],
+    )];
+    assert!(matches!(
+        writer.write(&wrong_type),
+        Err(EncodeError::InvalidValue { .. })
+    ));
+
+    let no_timestamp = [sample(None, vec![])];
+    assert_eq!(
+        writer
+            .clone()
+            .with_sample_timestamps(true)
+            .write(&no_timestamp),
+        Err(EncodeError::MissingTimestamp { sample: 0 })
+    );
+
+    let before_dc_epoch = [sample(Some(Timestamp::FileTime(0)), vec![])];
+    assert_eq!(
+        writer.clone().with_dc_time(true).write(&before_dc_epoch),
+        Err(EncodeError::TimestampOutOfRange {
+            timestamp: Timestamp::FileTime(0)
+        })
+    );
+
+    assert_eq!(
+        writer
+            .with_version(Version { major: 2, minor: 0 })
+            .write(&[]),
+        Err(EncodeError::UnsupportedVersion { major: 2, minor: 0 })
+    );
+}
+
+#[test]
+fn sample_too_long() {
+    let mut symbol_stream = ema();
+    let symbol = symbol_stream
+        .symbols
+        .iter_mut()
+        .find(|symbol| symbol.name == "Main.d_intValue")
+        .unwrap();
+    symbol.len = 1 << 32;
+    let len = symbol.sample_location().0 + (1 << 32);
+
+    assert_eq!(
+        DataStreamWriter::new(&symbol_stream).write(&[]),
+        Err(EncodeError::SampleTooLong { len })
+    );
+}
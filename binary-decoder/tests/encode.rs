@@ -0,0 +1,34 @@
+//! Round trips symbol streams through the encoder.
+
+mod common;
+
+use binary_decoder::SymbolStream;
+
+use common::{ema, EMA};
+
+#[test]
+fn ema_round_trip() {
+    let encoded = ema().encode();
+    assert_eq!(encoded.len(), EMA.len());
+    assert!(
+        encoded == EMA,
+        "encoded stream differs from ema.symbol_stream"
+    );
+}
+
+#[test]
+fn edited_stream_reparses() {
+    let mut symbol_stream = ema();
+
+    let symbol = &mut symbol_stream.symbols[0];
+    symbol.name = "Main.renamedWithALongerName".to_string();
+    symbol.comment = "Added by the test".to_string();
+    symbol_stream.data_types.truncate(3);
+
+    let reparsed = SymbolStream::parse(&symbol_stream.encode()).unwrap();
+    assert_eq!(reparsed.symbols[0].name, "Main.renamedWithALongerName");
+    assert_eq!(reparsed.symbols[0].comment, "Added by the test");
+    assert_eq!(reparsed.symbols.len(), symbol_stream.symbols.len());
+    assert_eq!(reparsed.data_types.len(), 3);
+    assert_eq!(reparsed.encode(), symbol_stream.encode());
+}
@@ -0,0 +1,102 @@
+//! Decodes the enumerators of ENUM data types and values of those types.
+
+mod common;
+
+use binary_decoder::{AdsDataType, EnumItem, PlcValue, SymbolStream};
+
+use common::{data_type_mut, ema, symbol};
+
+fn decode_enum(symbol_stream: &SymbolStream, bytes: &[u8]) -> PlcValue {
+    let symbol = symbol(symbol_stream, "Main.t_enumValue");
+    symbol_stream.decode_symbol(symbol, bytes).unwrap()
+}
+
+#[test]
+fn enum_items() {
+    let symbol_stream = ema();
+    let test_enum = symbol_stream.data_type("TestEnum").unwrap();
+    assert!(test_enum.is_enum());
+    assert!(!symbol_stream.data_type("INT").unwrap().is_enum());
+
+    let items: Vec<_> = test_enum
+        .enum_items
+        .iter()
+        .map(|item| (item.name.as_str(), item.value))
+        .collect();
+    assert_eq!(
+        items,
+        [
+            ("A_VALUE", 0),
+            ("B_VALUE", 1),
+            ("C_VALUE", 2),
+            ("D_VALUE", 3),
+            ("E_VALUE", 4),
+        ]
+    );
+
+    assert_eq!(test_enum.enum_name(3), Some("D_VALUE"));
+    assert_eq!(test_enum.enum_name(5), None);
+    assert_eq!(test_enum.enum_value("B_VALUE"), Some(1));
+    assert_eq!(test_enum.enum_value("F_VALUE"), None);
+}
+
+#[test]
+fn decode_values() {
+    let symbol_stream = ema();
+
+    let value = decode_enum(&symbol_stream, &[3, 0]);
+    assert_eq!(
+        value,
+        PlcValue::Enum {
+            type_name: "TestEnum".into(),
+            name: Some("D_VALUE".into()),
+            value: 3,
+        }
+    );
+    assert_eq!(value.to_string(), "TestEnum#D_VALUE");
+
+    // A value that is not one of the enumerators keeps its number
+    let value = decode_enum(&symbol_stream, &[9, 0]);
+    assert_eq!(
+        value,
+        PlcValue::Enum {
+            type_name: "TestEnum".into(),
+            name: None,
+            value: 9,
+        }
+    );
+    assert_eq!(value.to_string(), "TestEnum#9");
+}
+
+#[test]
+fn values_sized_by_base_type() {
+    // The same enumeration with a DINT base type, whose values take four bytes each
+    let mut symbol_stream = ema();
+    let test_enum = data_type_mut(&mut symbol_stream, "TestEnum");
+    test_enum.data_type_len = 4;
+    test_enum.base_data_type = AdsDataType::Int32;
+    test_enum.enum_items = vec![
+        EnumItem {
+            name: "NEGATIVE".into(),
+            value: -70_000,
+        },
+        EnumItem {
+            name: "POSITIVE".into(),
+            value: 70_000,
+        },
+    ];
+    let symbol_stream = SymbolStream::parse(&symbol_stream.encode()).unwrap();
+
+    let test_enum = symbol_stream.data_type("TestEnum").unwrap();
+    assert_eq!(test_enum.enum_value("NEGATIVE"), Some(-70_000));
+    assert_eq!(test_enum.enum_name(70_000), Some("POSITIVE"));
+
+    // The data type after the enumerators is still aligned
+    let next = symbol_stream
+        .data_types
+        .iter()
+        .position(|data_type| data_type.name == "TestEnum")
+        .unwrap()
+        + 1;
+    assert_eq!(symbol_stream.data_types[next].name, "TestStruct");
+}
@@ -0,0 +1,196 @@
+//! Checks the flag types against the bit layouts described in `symbol_stream.ksy`.
+
+use binary_decoder::{DataTypeFlags, MethodParameterFlags, StreamFlags, SymbolFlags};
+use bitflags::Flags;
+
+/// A flag field of a `.ksy` type: its id and the bits it occupies.
+#[derive(Debug)]
+struct KsyField {
+    id: String,
+    mask: u32,
+}
+
+/// Reads the bit fields of `ksy_type` from the spec, in declaration order, starting at bit 0.
+fn ksy_fields(ksy_type: &str) -> Vec<KsyField> {
+    let spec = include_str!("../symbol_stream.ksy");
+    let header = format!("  {ksy_type}:");
+
+    let mut lines = spec.lines().skip_while(|line| *line != header).skip(1);
+    let mut fields = vec![];
+    let mut id = None;
+    let mut bit = 0;
+    for line in lines.by_ref() {
+        // The next type starts at the same indentation as this one
+        if line.starts_with("  ") && !line.starts_with("   ") {
+            break;
+        }
+        let line = line.trim().trim_start_matches("- ");
+        if let Some(value) = line.strip_prefix("id: ") {
+            id = Some(value.to_string());
+        } else if let Some(value) = line.strip_prefix("type: b") {
+            let width: u32 = value.parse().expect("bit field width");
+            let mask = if width == 32 { !0 } else { ((1u32 << width) - 1) << bit };
+            fields.push(KsyField {
+                id: id.take().expect("type without id"),
+                mask,
+            });
+            bit += width;
+        }
+    }
+
+    assert!(!fields.is_empty(), "{ksy_type} not found in the spec");
+    assert_eq!(bit, 32, "{ksy_type} does not add up to 32 bits");
+    fields
+}
+
+fn is_reserved(field: &KsyField) -> bool {
+    field.id.starts_with("reserved")
+        || field.id.starts_with("unknown")
+        || field.id.starts_with("padding")
+}
+
+/// Checks that every named flag of `F` matches its field in the spec and that every
+/// non-reserved field of the spec has a flag.
+fn check<F: Flags<Bits = u32>>(ksy_type: &str, table: &[(&str, &str)]) {
+    let fields = ksy_fields(ksy_type);
+
+    for field in fields.iter().filter(|f| !is_reserved(f)) {
+        let (_, name) = table
+            .iter()
+            .find(|(id, _)| *id == field.id)
+            .unwrap_or_else(|| panic!("{ksy_type}.{} has no flag", field.id));
+        let flag = F::from_name(name).unwrap_or_else(|| panic!("no flag named {name}"));
+        assert_eq!(
+            flag.bits(),
+            field.mask,
+            "{name} does not match {ksy_type}.{}",
+            field.id
+        );
+    }
+
+    for flag in F::FLAGS.iter().filter(|f| f.is_named()) {
+        assert!(
+            table.iter().any(|(_, name)| *name == flag.name()),
+            "{} is not described by {ksy_type}",
+            flag.name()
+        );
+    }
+
+    for field in fields.iter().filter(|f| is_reserved(f)) {
+        let overlapping = F::FLAGS
+            .iter()
+            .filter(|f| f.is_named())
+            .find(|f| f.value().bits() & field.mask != 0);
+        assert!(
+            overlapping.is_none(),
+            "{} overlaps reserved {ksy_type}.{}",
+            overlapping.unwrap().name(),
+            field.id
+        );
+    }
+}
+
+#[test]
+fn stream_flags_match_spec() {
+    check::<StreamFlags>(
+        "stream_flags",
+        &[
+            ("is_online_change", "ONLINE_CHANGE"),
+            ("is_target_64_bit", "TARGET_64_BIT"),
+            ("are_base_types_included", "BASE_TYPES_INCLUDED"),
+            ("perform_q_sort", "PERFORM_Q_SORT"),
+        ],
+    );
+}
+
+#[test]
+fn symbol_flags_match_spec() {
+    check::<SymbolFlags>(
+        "symbol_flags",
+        &[
+            ("is_persistent", "PERSISTENT"),
+            ("is_bit_value", "BIT_VALUE"),
+            ("is_reference_to", "REFERENCE_TO"),
+            ("has_type_guid", "TYPE_GUID"),
+            (
+                "is_twincat_com_interface_pointer",
+                "TWINCAT_COM_INTERFACE_POINTER",
+            ),
+            ("is_read_only", "READ_ONLY"),
+            ("is_interface_method_access", "INTERFACE_METHOD_ACCESS"),
+            ("is_method_deref", "METHOD_DEREF"),
+            ("context_mask", "CONTEXT_MASK"),
+            ("has_attributes", "ATTRIBUTES"),
+            ("is_static", "STATIC"),
+            ("is_initialised_on_reset", "INITIALISED_ON_RESET"),
+            ("has_extended_flags", "EXTENDED_FLAGS"),
+        ],
+    );
+}
+
+#[test]
+fn data_type_flags_match_spec() {
+    check::<DataTypeFlags>(
+        "data_type_flags",
+        &[
+            ("is_data_type", "DATA_TYPE"),
+            ("is_data_item", "DATA_ITEM"),
+            ("is_reference_to", "REFERENCE_TO"),
+            ("is_method_deref", "METHOD_DEREF"),
+            ("is_oversampling_array", "OVERSAMPLING_ARRAY"),
+            ("is_bit_value", "BIT_VALUE"),
+            ("is_property_item", "PROPERTY_ITEM"),
+            ("has_type_guid", "TYPE_GUID"),
+            ("is_persistent", "PERSISTENT"),
+            ("has_copy_mask", "COPY_MASK"),
+            (
+                "is_twincat_com_interface_pointer",
+                "TWINCAT_COM_INTERFACE_POINTER",
+            ),
+            ("has_method_infos", "METHOD_INFOS"),
+            ("has_attributes", "ATTRIBUTES"),
+            ("has_enum_infos", "ENUM_INFOS"),
+            ("is_byte_aligned", "BYTE_ALIGNED"),
+            ("is_static", "STATIC"),
+            ("sp_levels", "SP_LEVELS"),
+            ("ignore_persist", "IGNORE_PERSIST"),
+            ("is_any_size_array", "ANY_SIZE_ARRAY"),
+            ("is_persistant_datatype", "PERSISTENT_DATA_TYPE"),
+            ("is_initialised_on_result", "INITIALISED_ON_RESULT"),
+        ],
+    );
+}
+
+#[test]
+fn method_parameter_flags_match_spec() {
+    check::<MethodParameterFlags>(
+        "method_parameter_flag",
+        &[("in", "IN"), ("out", "OUT"), ("reference", "REFERENCE")],
+    );
+}
+
+#[test]
+fn unknown_bits_are_preserved() {
+    for bits in [0, 1 << 14, 1 << 31, 0xdead_beef, !0] {
+        assert_eq!(StreamFlags::from(bits).bits(), bits);
+        assert_eq!(SymbolFlags::from(bits).bits(), bits);
+        assert_eq!(DataTypeFlags::from(bits).bits(), bits);
+        assert_eq!(MethodParameterFlags::from(bits).bits(), bits);
+    }
+}
+
+#[test]
+fn context_does_not_imply_attributes() {
+    for context in 0..16 {
+        let flags = SymbolFlags::from(context << 8);
+        assert_eq!(flags.context_mask(), context as u8);
+        assert!(!flags.contains(SymbolFlags::ATTRIBUTES));
+    }
+}
+
+#[test]
+fn unset_flags_are_not_reported() {
+    assert!(DataTypeFlags::from(0).is_empty());
+    assert!(!DataTypeFlags::from(1).contains(DataTypeFlags::STATIC));
+    assert!(!DataTypeFlags::from(1).contains(DataTypeFlags::SP_LEVELS));
+}
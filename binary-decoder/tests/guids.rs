@@ -0,0 +1,69 @@
+//! Decodes the mixed-endian type GUIDs of symbols and data types and the header layout GUID.
+
+mod common;
+
+use binary_decoder::{SymbolFlags, SymbolStream};
+use uuid::uuid;
+
+use common::{ema, symbol, EMA, LAYOUT, LAYOUT_OFFSET};
+
+#[test]
+fn layout() {
+    assert_eq!(
+        ema().header.layout,
+        uuid!("4679fba2-beaf-ff6a-338f-451f837261f8")
+    );
+    // The first three groups are little endian, the last eight bytes are in order
+    assert_eq!(
+        EMA[LAYOUT_OFFSET..LAYOUT_OFFSET + 16],
+        [
+            0xa2, 0xfb, 0x79, 0x46, 0xaf, 0xbe, 0x6a, 0xff, 0x33, 0x8f, 0x45, 0x1f, 0x83, 0x72,
+            0x61, 0xf8
+        ]
+    );
+    assert_eq!(EMA[LAYOUT_OFFSET..LAYOUT_OFFSET + 16], LAYOUT);
+}
+
+#[test]
+fn type_guids() {
+    let symbol_stream = ema();
+    let int = uuid!("18071995-0000-0000-0000-000000000006");
+    assert_eq!(symbol_stream.data_type("INT").unwrap().guid, Some(int));
+    assert_eq!(
+        symbol_stream.data_type("TestStruct").unwrap().guid,
+        Some(uuid!("8465929a-58d8-70f3-5f6c-1f86134124a3"))
+    );
+
+    // Symbols carry the GUID of their data type
+    let names = [
+        "Main.d_intValue",
+        "Main.p_intArrayValue",
+        "Main.r_structValue",
+        "Main.r_structValue.d_bit3Member",
+        "Main.t_enumValue",
+    ];
+    for name in names {
+        let symbol = symbol(&symbol_stream, name);
+        assert!(symbol.flags.contains(SymbolFlags::TYPE_GUID));
+        let data_type = symbol_stream.data_type(&symbol.data_type_name).unwrap();
+        assert_eq!(symbol.data_type_guid, data_type.guid, "{}", symbol.name);
+    }
+}
+
+#[test]
+fn symbol_without_type_guid() {
+    let mut symbol_stream = ema();
+    symbol_stream.symbols[0]
+        .flags
+        .remove(SymbolFlags::TYPE_GUID);
+    let symbol_stream = SymbolStream::parse(&symbol_stream.encode()).unwrap();
+    assert_eq!(symbol_stream.symbols[0].data_type_guid, None);
+
+    // The next symbol is read from where the GUID would have been
+    let next = &symbol_stream.symbols[1];
+    assert_eq!(next.name, "Main.b_sintValue");
+    assert_eq!(
+        next.data_type_guid,
+        Some(uuid!("18071995-0000-0000-0000-000000000003"))
+    );
+}
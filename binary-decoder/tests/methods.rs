@@ -0,0 +1,108 @@
+//! Parses the method infos of a function block from a hand-built symbol stream.
+
+mod common;
+
+use binary_decoder::{AdsDataType, DataTypeFlags, MethodParameterFlags, SymbolStream};
+use uuid::{uuid, Uuid};
+
+use common::{data_type, record, symbol_stream, texts, u32s};
+
+const RETURN_TYPE_GUID: [u8; 16] = [
+    0x78, 0x56, 0x34, 0x12, 0x34, 0x12, 0x78, 0x56, 0x9A, 0xBC, 0xDE, 0xF0, 0x12, 0x34, 0x56, 0x78,
+];
+
+/// `step : INT` passed by value, with a zero type GUID.
+fn parameter() -> Vec<u8> {
+    let mut body = vec![];
+    // Size, alignment, data type, flags, reserved
+    u32s(&mut body, &[2, 2, AdsDataType::Int16 as u32, 1, 0]);
+    body.extend([0; 16]);
+    // Length-is parameter
+    body.extend(0u16.to_le_bytes());
+    texts(&mut body, &[], &["step", "INT", "Amount to add"]);
+    record(body)
+}
+
+/// `METHOD Increment : DINT` with a single parameter.
+fn method() -> Vec<u8> {
+    let mut body = vec![];
+    // Version, vtable index, return size, return alignment, reserved
+    u32s(&mut body, &[1, 3, 4, 4, 0]);
+    body.extend(RETURN_TYPE_GUID);
+    // Return data type, flags
+    u32s(&mut body, &[AdsDataType::Int32 as u32, 0]);
+    texts(&mut body, &[1], &["Increment", "DINT", "Adds to the count"]);
+    body.extend(parameter());
+    record(body)
+}
+
+/// A stream without symbols that declares the function block `FB_Counter`, which has a
+/// method, followed by a plain struct `ST_Next`.
+fn counter() -> Vec<u8> {
+    let mut methods = 1u16.to_le_bytes().to_vec();
+    methods.extend(method());
+    let data_types = [
+        data_type(
+            "FB_Counter",
+            16,
+            DataTypeFlags::DATA_TYPE | DataTypeFlags::METHOD_INFOS,
+            &methods,
+        ),
+        data_type("ST_Next", 8, DataTypeFlags::DATA_TYPE, &[]),
+    ]
+    .concat();
+    symbol_stream(2, &data_types)
+}
+
+#[test]
+fn method_infos() {
+    let symbol_stream = SymbolStream::parse(&counter()).unwrap();
+    assert_eq!(symbol_stream.data_types.len(), 2);
+
+    let counter = &symbol_stream.data_types[0];
+    assert_eq!(counter.name, "FB_Counter");
+    assert_eq!(counter.methods.len(), 1);
+
+    let method = &counter.methods[0];
+    assert_eq!(method.version, 1);
+    assert_eq!(method.vtable_index, 3);
+    assert_eq!(method.return_size, 4);
+    assert_eq!(method.return_align_size, 4);
+    assert_eq!(
+        method.return_type_guid,
+        uuid!("12345678-1234-5678-9abc-def012345678")
+    );
+    assert_eq!(method.return_data_type, AdsDataType::Int32);
+    assert_eq!(method.name, "Increment");
+    assert_eq!(method.return_type, "DINT");
+    assert_eq!(method.comment, "Adds to the count");
+
+    assert_eq!(method.parameters.len(), 1);
+    let parameter = &method.parameters[0];
+    assert_eq!(parameter.size, 2);
+    assert_eq!(parameter.align_size, 2);
+    assert_eq!(parameter.data_type, AdsDataType::Int16);
+    assert_eq!(parameter.flags, MethodParameterFlags::IN);
+    assert_eq!(parameter.type_guid, Uuid::nil());
+    assert_eq!(parameter.name, "step");
+    assert_eq!(parameter.type_name, "INT");
+    assert_eq!(parameter.comment, "Amount to add");
+
+    // The data type after the method infos still starts where expected
+    let next = &symbol_stream.data_types[1];
+    assert_eq!(next.name, "ST_Next");
+    assert_eq!(next.data_type_len, 8);
+    assert!(next.methods.is_empty());
+}
+
+#[test]
+fn round_trip() {
+    let symbol_stream = SymbolStream::parse(&counter()).unwrap();
+    let reparsed = SymbolStream::parse(&symbol_stream.encode()).unwrap();
+
+    let method = &reparsed.data_types[0].methods[0];
+    assert_eq!(method.name, "Increment");
+    assert_eq!(method.parameters[0].name, "step");
+    assert_eq!(method.parameters[0].flags, MethodParameterFlags::IN);
+    assert_eq!(reparsed.data_types[1].name, "ST_Next");
+}
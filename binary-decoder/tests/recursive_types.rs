@@ -0,0 +1,105 @@
+//! Decodes and encodes a data type whose member refers back to the type itself, and parses
+//! members nested as deep as values are decoded.
+
+mod common;
+
+use std::sync::Arc;
+
+use binary_decoder::{
+    AdsDataType, DataStream, DataStreamWriter, DataTypeFlags, DecodeError, EncodeError,
+    NamedValue, PlcValue, Sample, SampleDecoder, SymbolStream, SymbolStreamRef,
+};
+
+use common::{data_type_mut, ema, nested, symbol};
+
+/// The ema stream with the first member of `TestStruct` made a `TestStruct` of its own.
+fn recursive() -> SymbolStream {
+    let mut symbol_stream = ema();
+    let test_struct = data_type_mut(&mut symbol_stream, "TestStruct");
+    let len = test_struct.data_type_len;
+    let member = &mut test_struct.sub_items[0];
+    member.data_type_name = "TestStruct".to_string();
+    member.data_type_len = len;
+    member.offset = 0;
+    member.base_data_type = AdsDataType::BigType;
+    member.flags.remove(DataTypeFlags::BIT_VALUE);
+
+    // Survives the encoder, as a stream received from the wire would
+    SymbolStream::parse(&symbol_stream.encode()).unwrap()
+}
+
+#[test]
+fn decode_symbol() {
+    let symbol_stream = recursive();
+    let symbol = symbol(&symbol_stream, "Main.r_structValue");
+
+    let bytes = vec![0; symbol.len];
+    assert!(matches!(
+        symbol_stream.decode_symbol(symbol, &bytes),
+        Err(DecodeError::NestingTooDeep { .. })
+    ));
+}
+
+#[test]
+fn decode_samples() {
+    let symbol_stream = recursive();
+    let message = DataStreamWriter::new(&symbol_stream)
+        .write(&[Sample {
+            timestamp: None,
+            values: vec![],
+        }])
+        .unwrap();
+    let stream = DataStream::parse(&message).unwrap();
+
+    assert!(matches!(
+        SampleDecoder::new(&symbol_stream).decode(&stream),
+        Err(DecodeError::NestingTooDeep { .. })
+    ));
+}
+
+#[test]
+fn encode_samples() {
+    let symbol_stream = recursive();
+    let sample = Sample {
+        timestamp: None,
+        values: vec![NamedValue {
+            name: Arc::from("Main.r_structValue"),
+            value: PlcValue::Struct(vec![]),
+        }],
+    };
+
+    // A struct value without members writes nothing, so give the member a value at every level
+    let mut value = PlcValue::Int(0);
+    for _ in 0..100 {
+        value = PlcValue::Struct(vec![("a_boolMember".to_string(), value)]);
+    }
+    let nested = Sample {
+        timestamp: None,
+        values: vec![NamedValue {
+            name: Arc::from("Main.r_structValue"),
+            value,
+        }],
+    };
+
+    let writer = DataStreamWriter::new(&symbol_stream);
+    assert!(writer.write(&[sample]).is_ok());
+    assert!(matches!(
+        writer.write(&[nested]),
+        Err(EncodeError::InvalidValue { .. })
+    ));
+}
+
+#[test]
+fn parse_members() {
+    // The parser follows member records exactly as deep as values are decoded
+    assert!(SymbolStream::parse(&nested(64)).is_ok());
+
+    let data = nested(65);
+    let error = SymbolStream::parse(&data).unwrap_err();
+    assert!(
+        matches!(error, DecodeError::NestingTooDeep { .. }),
+        "{error:?}"
+    );
+    let stream = SymbolStreamRef::parse(&data).unwrap();
+    assert_eq!(stream.into_owned().unwrap_err(), error);
+}
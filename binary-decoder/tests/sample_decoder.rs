@@ -0,0 +1,115 @@
+//! Decodes hand-built samples with the layout of `ema.symbol_stream`.
+
+mod common;
+
+use binary_decoder::{
+    DataStream, DataStreamFlags, DataStreamWriter, DecodeError, PlcValue, SampleDecoder,
+};
+
+use common::{ema, header_1_0};
+
+/// A 1.0 data stream message with the ema layout and `samples`.
+fn message(sample_data_len: usize, samples: &[Vec<u8>]) -> Vec<u8> {
+    let mut message = header_1_0(sample_data_len as u32, DataStreamFlags::empty());
+    for sample in samples {
+        message.extend(sample);
+    }
+    message
+}
+
+/// A sample with a few symbols set, at their offsets in the ema layout.
+fn sample(sample_data_len: usize) -> Vec<u8> {
+    let mut data = vec![0; sample_data_len];
+    // Main.a_boolValue
+    data[0] = 1;
+    // Main.d_intValue
+    data[3..5].copy_from_slice(&(-1234i16).to_le_bytes());
+    // Main.j_realValue
+    data[15..19].copy_from_slice(&2.5f32.to_le_bytes());
+    // Main.l_stringValue
+    data[43..48].copy_from_slice(b"hello");
+    // Main.r_structValue.d_bit3Member
+    data[372] = 1 << 3;
+    // Main.t_enumValue
+    data[394..396].copy_from_slice(&4u16.to_le_bytes());
+    data
+}
+
+#[test]
+fn decode_sample() {
+    let symbol_stream = ema();
+    let len = DataStreamWriter::new(&symbol_stream).sample_data_len();
+    let message = message(len, &[sample(len)]);
+    let stream = DataStream::parse(&message).unwrap();
+
+    let decoder = SampleDecoder::new(&symbol_stream);
+    assert_eq!(decoder.layout(), symbol_stream.header.layout);
+    let samples = decoder.decode(&stream).unwrap();
+    assert_eq!(samples.len(), 1);
+
+    let sample = &samples[0];
+    assert_eq!(sample.values.len(), symbol_stream.symbols.len());
+    for (value, symbol) in sample.values.iter().zip(&symbol_stream.symbols) {
+        assert_eq!(&*value.name, symbol.name);
+    }
+    assert_eq!(sample.get("Main.a_boolValue"), Some(&PlcValue::Bool(true)));
+    assert_eq!(sample.get("Main.b_sintValue"), Some(&PlcValue::SInt(0)));
+    assert_eq!(sample.get("Main.d_intValue"), Some(&PlcValue::Int(-1234)));
+    assert_eq!(sample.get("Main.j_realValue"), Some(&PlcValue::Real(2.5)));
+    assert_eq!(
+        sample.get("Main.l_stringValue"),
+        Some(&PlcValue::String("hello".into()))
+    );
+    assert_eq!(
+        sample.get("Main.r_structValue.d_bit3Member"),
+        Some(&PlcValue::Bool(true))
+    );
+    assert_eq!(
+        sample.get("Main.r_structValue.d_bit2Member"),
+        Some(&PlcValue::Bool(false))
+    );
+    assert_eq!(
+        sample.get("Main.t_enumValue").and_then(PlcValue::as_str),
+        Some("E_VALUE")
+    );
+    assert_eq!(sample.get("Main.missing"), None);
+}
+
+#[test]
+fn layout_mismatch() {
+    let symbol_stream = ema();
+    let len = DataStreamWriter::new(&symbol_stream).sample_data_len();
+    let mut message = message(len, &[sample(len)]);
+    // Another layout GUID
+    message[16..32].fill(0x11);
+    let stream = DataStream::parse(&message).unwrap();
+
+    assert_eq!(
+        SampleDecoder::new(&symbol_stream)
+            .decode(&stream)
+            .unwrap_err(),
+        DecodeError::LayoutMismatch {
+            expected: symbol_stream.header.layout,
+            actual: stream.header.layout,
+        }
+    );
+}
+
+#[test]
+fn short_sample() {
+    let symbol_stream = ema();
+    let len = DataStreamWriter::new(&symbol_stream).sample_data_len() - 2;
+    let message = message(len, &[vec![0; len]]);
+    let stream = DataStream::parse(&message).unwrap();
+
+    // Main.t_enumValue is the last symbol, and lies beyond the end of the sample
+    assert_eq!(
+        SampleDecoder::new(&symbol_stream)
+            .decode(&stream)
+            .unwrap_err(),
+        DecodeError::Truncated {
+            offset: len,
+            needed: 2,
+        }
+    );
+}
@@ -0,0 +1,137 @@
+//! Parses the members of struct data types, including structs nested in structs.
+
+mod common;
+
+use binary_decoder::{
+    AdsDataType, DataTypeFlags, DecodeError, PlcValue, SymbolStream, SymbolStreamRef,
+};
+
+use common::{ema, nested, symbol};
+
+#[test]
+fn struct_members() {
+    let symbol_stream = ema();
+    let test_struct = symbol_stream.data_type("TestStruct").unwrap();
+    assert!(test_struct.is_struct());
+    assert_eq!(test_struct.sub_item_count, 9);
+
+    let members: Vec<_> = test_struct
+        .sub_items
+        .iter()
+        .map(|item| {
+            (
+                item.name.as_str(),
+                item.data_type_name.as_str(),
+                item.offset,
+            )
+        })
+        .collect();
+    assert_eq!(
+        members,
+        [
+            ("a_boolMember", "BOOL", 0),
+            ("b_sintMember", "SINT", 1),
+            ("c_intMember", "INT", 2),
+            // Bit members are located in bits
+            ("d_bit0Member", "BIT", 32),
+            ("d_bit1Member", "BIT", 33),
+            ("d_bit2Member", "BIT", 34),
+            ("d_bit3Member", "BIT", 35),
+            ("d_bit4Member", "BIT", 36),
+            ("d_bit5Member", "BIT", 37),
+        ]
+    );
+    for item in &test_struct.sub_items {
+        assert!(item.flags.contains(DataTypeFlags::DATA_ITEM));
+        assert!(!item.is_struct());
+    }
+
+    let member = test_struct.sub_item("c_intMember").unwrap();
+    assert_eq!(member.base_data_type, AdsDataType::Int16);
+    assert_eq!(member.data_type_len, 2);
+    assert!(test_struct
+        .sub_item("d_bit4Member")
+        .unwrap()
+        .flags
+        .contains(DataTypeFlags::BIT_VALUE));
+    assert!(test_struct.sub_item("e_missing").is_none());
+    assert!(!symbol_stream.data_type("INT").unwrap().is_struct());
+}
+
+#[test]
+fn nested_structs() {
+    let symbol_stream = ema();
+    let super_struct = symbol_stream.data_type("SuperStruct").unwrap();
+    assert_eq!(super_struct.data_type_len, 8);
+
+    let inner = super_struct.sub_item("a_testStruct").unwrap();
+    assert_eq!(inner.data_type_name, "TestStruct");
+    assert_eq!(inner.offset, 0);
+    assert_eq!(inner.data_type_len, 6);
+    // Members refer to their type by name, where its own members are found
+    assert_eq!(
+        symbol_stream
+            .data_type(&inner.data_type_name)
+            .unwrap()
+            .sub_items
+            .len(),
+        9
+    );
+
+    let after = super_struct.sub_item("b_intValue").unwrap();
+    assert_eq!(after.offset, 6);
+    assert_eq!(after.data_type_len, 2);
+}
+
+#[test]
+fn decode_nested_struct() {
+    let symbol_stream = ema();
+    let symbol = symbol(&symbol_stream, "Main.s_nestedStructValue");
+
+    // a_boolMember, b_sintMember, c_intMember, the bit members, then b_intValue
+    let bytes = [1, 0xFE, 0x34, 0x12, 0b10_0001, 0, 0x07, 0x00];
+    let value = symbol_stream.decode_symbol(symbol, &bytes).unwrap();
+
+    let inner = value.member("a_testStruct").unwrap();
+    assert_eq!(inner.member("a_boolMember"), Some(&PlcValue::Bool(true)));
+    assert_eq!(inner.member("b_sintMember"), Some(&PlcValue::SInt(-2)));
+    assert_eq!(inner.member("c_intMember"), Some(&PlcValue::Int(0x1234)));
+    assert_eq!(inner.member("d_bit0Member"), Some(&PlcValue::Bool(true)));
+    assert_eq!(inner.member("d_bit1Member"), Some(&PlcValue::Bool(false)));
+    assert_eq!(inner.member("d_bit5Member"), Some(&PlcValue::Bool(true)));
+    assert_eq!(value.member("b_intValue"), Some(&PlcValue::Int(7)));
+}
+
+#[test]
+fn deeply_nested_members() {
+    // Converting members recursively would overflow the stack long before this
+    let data = nested(100_000);
+    let error = SymbolStream::parse(&data).unwrap_err();
+    assert!(
+        matches!(error, DecodeError::NestingTooDeep { offset } if offset > 64),
+        "{error:?}"
+    );
+
+    // Walking the members of the borrowed stream stops at the same depth
+    let stream = SymbolStreamRef::parse(&data).unwrap();
+    let mut data_type = stream.data_types().next().unwrap().unwrap();
+    let mut depth = 0;
+    let error = loop {
+        match data_type.sub_items().next().unwrap() {
+            Ok(member) => data_type = member,
+            Err(e) => break e,
+        }
+        depth += 1;
+    };
+    assert_eq!(error, SymbolStream::parse(&data).unwrap_err());
+    assert_eq!(depth, 64);
+
+    // Members nested as deep as values are decoded are fine
+    let symbol_stream = SymbolStream::parse(&nested(64)).unwrap();
+    let mut data_type = &symbol_stream.data_types[0];
+    for level in 1..=64 {
+        data_type = &data_type.sub_items[0];
+        assert_eq!(data_type.name, format!("T{level}"));
+    }
+    assert!(data_type.sub_items.is_empty());
+}
@@ -0,0 +1,101 @@
+//! Compares the borrowing view of `ema.symbol_stream` with the owned decoding.
+
+mod common;
+
+use binary_decoder::{DecodeError, SymbolStream, SymbolStreamRef};
+
+use common::EMA;
+
+#[test]
+fn matches_owned() {
+    let owned = SymbolStream::parse(EMA).unwrap();
+    let borrowed = SymbolStreamRef::parse(EMA).unwrap();
+
+    let symbols: Vec<_> = borrowed.symbols().map(Result::unwrap).collect();
+    assert_eq!(symbols.len(), owned.symbols.len());
+    for (symbol, expected) in symbols.iter().zip(&owned.symbols) {
+        assert_eq!(symbol.name, expected.name);
+        assert_eq!(symbol.data_type_name, expected.data_type_name);
+        assert_eq!(symbol.index_offset, expected.index_offset);
+        assert_eq!(symbol.attributes.len(), expected.attributes.len());
+    }
+
+    let data_types: Vec<_> = borrowed.data_types().map(Result::unwrap).collect();
+    assert_eq!(data_types.len(), owned.data_types.len());
+    for (data_type, expected) in data_types.iter().zip(&owned.data_types) {
+        assert_eq!(data_type.name, expected.name);
+        assert_eq!(
+            data_type.array_dimensions().collect::<Vec<_>>(),
+            expected.array_dimensions
+        );
+        assert_eq!(data_type.sub_items().count(), expected.sub_items.len());
+        let enum_items: Vec<_> = data_type
+            .enum_items()
+            .map(|i| (i.name.into_owned(), i.value))
+            .collect();
+        let expected_items: Vec<_> = expected
+            .enum_items
+            .iter()
+            .map(|i| (i.name.clone(), i.value))
+            .collect();
+        assert_eq!(enum_items, expected_items);
+    }
+
+    let converted = borrowed.into_owned().unwrap();
+    assert_eq!(converted.encode(), owned.encode());
+}
+
+#[test]
+fn filter_without_decoding_everything() {
+    let borrowed = SymbolStreamRef::parse(EMA).unwrap();
+
+    let struct_members: Vec<_> = borrowed
+        .symbols()
+        .map(Result::unwrap)
+        .filter(|symbol| symbol.name.starts_with("Main.r_structValue."))
+        .map(|symbol| symbol.name)
+        .collect();
+    assert_eq!(struct_members.len(), 9);
+
+    let test_struct = borrowed
+        .data_types()
+        .map(Result::unwrap)
+        .find(|data_type| data_type.name == "TestStruct")
+        .unwrap();
+    let members: Vec<_> = test_struct
+        .sub_items()
+        .map(|member| member.unwrap().name)
+        .collect();
+    assert_eq!(
+        members[..3],
+        ["a_boolMember", "b_sintMember", "c_intMember"]
+    );
+}
+
+#[test]
+fn errors_are_reported_lazily() {
+    let owned = SymbolStream::parse(EMA).unwrap();
+    let mut data = EMA.to_vec();
+
+    // Corrupt the length prefix of the second symbol record
+    let header_len = owned.header.header_len as usize;
+    let first_len = u32::from_le_bytes(data[header_len..header_len + 4].try_into().unwrap());
+    let second = header_len + first_len as usize;
+    data[second..second + 4].copy_from_slice(&2u32.to_le_bytes());
+
+    let borrowed = SymbolStreamRef::parse(&data).unwrap();
+    let mut symbols = borrowed.symbols();
+    assert!(symbols.next().unwrap().is_ok());
+    assert_eq!(
+        symbols.next().unwrap().unwrap_err(),
+        DecodeError::LengthMismatch {
+            offset: second,
+            expected: 4,
+            actual: 2
+        }
+    );
+    assert!(symbols.next().is_none());
+    assert!(borrowed.data_types().all(|data_type| data_type.is_ok()));
+
+    assert!(SymbolStream::parse(&data).is_err());
+}
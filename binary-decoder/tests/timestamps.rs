@@ -0,0 +1,110 @@
+//! Checks the conversion of data stream times and the per-sample timestamps.
+
+mod common;
+
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use binary_decoder::{DataStream, DataStreamFlags, Timestamp};
+
+use common::header_1_1;
+
+/// FILETIME of the Unix epoch.
+const UNIX_EPOCH_FILETIME: u64 = 116_444_736_000_000_000;
+
+/// A 1.1 data stream of `samples` one byte samples, one cycle apart. Per-sample timestamps
+/// are 700 ns apart instead.
+fn stream(flags: DataStreamFlags, start_time: u64, samples: &[u8]) -> Vec<u8> {
+    let with_timestamps = flags.contains(DataStreamFlags::SAMPLE_TIMESTAMP);
+    let mut message = header_1_1(1, flags, samples.len() as u64, start_time);
+    for (i, sample) in samples.iter().enumerate() {
+        if with_timestamps {
+            message.extend((start_time + 7 * i as u64).to_le_bytes());
+        }
+        message.push(*sample);
+    }
+    message
+}
+
+#[test]
+fn epochs() {
+    assert_eq!(
+        Timestamp::FileTime(UNIX_EPOCH_FILETIME).to_system_time(),
+        UNIX_EPOCH
+    );
+    assert_eq!(
+        Timestamp::DcTime(0).to_system_time(),
+        UNIX_EPOCH + Duration::from_secs(946_684_800)
+    );
+    assert_eq!(
+        Timestamp::FileTime(0).to_string(),
+        "1601-01-01T00:00:00.000000000Z"
+    );
+    assert_eq!(
+        Timestamp::DcTime(0).to_string(),
+        "2000-01-01T00:00:00.000000000Z"
+    );
+}
+
+#[test]
+fn sub_second_precision() {
+    let filetime = Timestamp::FileTime(UNIX_EPOCH_FILETIME + 12_345_678);
+    assert_eq!(
+        SystemTime::from(filetime),
+        UNIX_EPOCH + Duration::from_nanos(1_234_567_800)
+    );
+    assert_eq!(filetime.to_string(), "1970-01-01T00:00:01.234567800Z");
+
+    let dc_time = Timestamp::DcTime(798_336_000_000_000_001);
+    assert_eq!(dc_time.to_string(), "2025-04-19T00:00:00.000000001Z");
+}
+
+#[test]
+fn interpolated_from_start_time() {
+    let flags = DataStreamFlags::HEAD_TIMESTAMP | DataStreamFlags::DC_TIME;
+    let message = stream(flags, 5_000, &[1, 2, 3]);
+    let stream = DataStream::parse(&message).unwrap();
+
+    assert_eq!(stream.header.start(), Some(Timestamp::DcTime(5_000)));
+    let timestamps: Vec<_> = stream.samples().map(|s| s.timestamp).collect();
+    assert_eq!(
+        timestamps,
+        [
+            Some(Timestamp::DcTime(5_000)),
+            Some(Timestamp::DcTime(1_005_000)),
+            Some(Timestamp::DcTime(2_005_000)),
+        ]
+    );
+}
+
+#[test]
+fn from_sample_header() {
+    let flags = DataStreamFlags::HEAD_TIMESTAMP | DataStreamFlags::SAMPLE_TIMESTAMP;
+    let message = stream(flags, UNIX_EPOCH_FILETIME, &[1, 2]);
+    let stream = DataStream::parse(&message).unwrap();
+
+    let timestamps: Vec<_> = stream.samples().map(|s| s.timestamp).collect();
+    assert_eq!(
+        timestamps,
+        [
+            Some(Timestamp::FileTime(UNIX_EPOCH_FILETIME)),
+            Some(Timestamp::FileTime(UNIX_EPOCH_FILETIME + 7)),
+        ]
+    );
+}
+
+#[test]
+fn without_timestamps() {
+    let message = stream(DataStreamFlags::empty(), 5_000, &[1, 2]);
+    let stream = DataStream::parse(&message).unwrap();
+
+    assert_eq!(stream.header.start(), None);
+    assert!(stream.samples().all(|s| s.timestamp.is_none()));
+}
+
+#[cfg(feature = "chrono")]
+#[test]
+fn chrono() {
+    let date_time = Timestamp::DcTime(1_500_000_000).to_chrono();
+    assert_eq!(date_time.timestamp(), 946_684_801);
+    assert_eq!(date_time.timestamp_subsec_nanos(), 500_000_000);
+}
@@ -0,0 +1,95 @@
+//! Parses symbol streams that are cut short, overrun their records or have an unknown version.
+
+mod common;
+
+use binary_decoder::{DecodeError, SymbolStream, SymbolStreamRef};
+
+use common::{EMA, HEADER_LEN};
+
+/// Offset of the name length within a symbol record.
+const SYMBOL_NAME_LEN_OFFSET: usize = 24;
+
+fn with_version(major: u8, minor: u8) -> Vec<u8> {
+    let mut data = EMA.to_vec();
+    data[0] = major;
+    data[1] = minor;
+    data
+}
+
+#[test]
+fn truncated_header() {
+    for len in 0..HEADER_LEN {
+        assert!(
+            matches!(
+                SymbolStream::parse(&EMA[..len]),
+                Err(DecodeError::Truncated { .. })
+            ),
+            "header cut at {len}"
+        );
+    }
+}
+
+#[test]
+fn header_shorter_than_its_fields() {
+    let mut data = EMA.to_vec();
+    data[2..4].copy_from_slice(&32u16.to_le_bytes());
+    assert_eq!(
+        SymbolStream::parse(&data).unwrap_err(),
+        DecodeError::LengthMismatch {
+            offset: 2,
+            expected: HEADER_LEN,
+            actual: 32,
+        }
+    );
+}
+
+#[test]
+fn truncated_records() {
+    for len in HEADER_LEN..EMA.len() {
+        assert!(SymbolStream::parse(&EMA[..len]).is_err(), "cut at {len}");
+
+        // The borrowing view locates every record up front, but decodes them one at a time
+        if let Ok(symbol_stream) = SymbolStreamRef::parse(&EMA[..len]) {
+            let symbols = symbol_stream.symbols().collect::<Result<Vec<_>, _>>();
+            let data_types = symbol_stream.data_types().collect::<Result<Vec<_>, _>>();
+            assert!(symbols.is_err() || data_types.is_err(), "cut at {len}");
+        }
+    }
+}
+
+#[test]
+fn record_longer_than_stream() {
+    let mut data = EMA.to_vec();
+    data[HEADER_LEN..HEADER_LEN + 4].copy_from_slice(&u32::MAX.to_le_bytes());
+    assert!(matches!(
+        SymbolStream::parse(&data),
+        Err(DecodeError::Truncated { offset: HEADER_LEN, .. })
+    ));
+}
+
+#[test]
+fn field_longer_than_record() {
+    let mut data = EMA.to_vec();
+    let name_len = HEADER_LEN + SYMBOL_NAME_LEN_OFFSET;
+    data[name_len..name_len + 2].copy_from_slice(&u16::MAX.to_le_bytes());
+    assert!(matches!(
+        SymbolStream::parse(&data),
+        Err(DecodeError::Truncated { .. })
+    ));
+}
+
+#[test]
+fn versions() {
+    let symbol_stream = SymbolStream::parse(EMA).unwrap();
+    assert_eq!(symbol_stream.header.version.to_string(), "3.0");
+
+    // Minor revisions may append to the header, which its length covers
+    assert!(SymbolStream::parse(&with_version(3, 7)).is_ok());
+    for (major, minor) in [(4, 0), (255, 1)] {
+        assert_eq!(
+            SymbolStream::parse(&with_version(major, minor)).unwrap_err(),
+            DecodeError::UnsupportedVersion { major, minor }
+        );
+        assert!(SymbolStreamRef::parse(&with_version(major, minor)).is_err());
+    }
+}
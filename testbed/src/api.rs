@@ -0,0 +1,25 @@
+use std::io;
+
+use axum::extract::State;
+use axum::routing::get;
+use axum::{Json, Router};
+use serde_json::Value;
+use tokio::net::TcpListener;
+
+use crate::registry::Registry;
+
+/// Serves the HTTP API on `listener`:
+///
+/// - `GET /streams`: the streams seen so far, with their layout, symbol count and the time of
+///   their last message.
+pub async fn serve(listener: TcpListener, registry: Registry) -> io::Result<()> {
+    let app = Router::new()
+        .route("/streams", get(streams))
+        .with_state(registry);
+    axum::serve(listener, app).await
+}
+
+async fn streams(State(registry): State<Registry>) -> Json<Value> {
+    let streams = registry.streams().iter().map(|s| s.to_json()).collect();
+    Json(Value::Array(streams))
+}
@@ -0,0 +1,589 @@
+use std::fs;
+use std::net::SocketAddr;
+use std::path::{Path, PathBuf};
+
+use serde::Deserialize;
+
+use crate::BoxError;
+
+/// The ingestion service configuration, read from a TOML or YAML file.
+///
+/// See `testbed.example.toml` for an annotated example.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Config {
+    pub brokers: Vec<BrokerConfig>,
+    #[serde(default)]
+    pub streams: Vec<StreamConfig>,
+    /// Where events go. Events are written to standard output if no sink is configured.
+    #[serde(default)]
+    pub sinks: Vec<SinkConfig>,
+    /// The HTTP API, not served if left out.
+    pub api: Option<ApiConfig>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct BrokerConfig {
+    /// Name streams refer to the broker by.
+    pub name: String,
+    pub host: String,
+    /// Defaults to 8883 with TLS and 1883 without.
+    pub port: Option<u16>,
+    /// Client id to connect with. Must be unique per broker; defaults to one derived from the
+    /// process id.
+    pub client_id: Option<String>,
+    /// Keep alive interval in seconds.
+    #[serde(default = "default_keep_alive")]
+    pub keep_alive: u64,
+    /// Largest packet accepted from the broker, in bytes. Symbol streams of large projects
+    /// run to several megabytes.
+    #[serde(default = "default_max_packet_size")]
+    pub max_packet_size: u32,
+    pub credentials: Option<Credentials>,
+    /// Connect over TLS instead of plain TCP.
+    pub tls: Option<TlsConfig>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct TlsConfig {
+    /// PEM file of the certificate authorities to trust instead of the system's.
+    pub ca: Option<PathBuf>,
+    /// PEM file of the client certificate chain, for brokers that authenticate clients by
+    /// certificate.
+    pub client_cert: Option<PathBuf>,
+    /// PEM private key of the client certificate.
+    #[serde(default, with = "serde_yaml::with::singleton_map")]
+    pub client_key: Option<Secret>,
+    /// Protocols to offer by ALPN, e.g. `["mqtt"]` for brokers sharing port 443.
+    #[serde(default)]
+    pub alpn: Vec<String>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Credentials {
+    pub username: String,
+    // YAML would otherwise expect a tag such as `!env MQTT_PASSWORD`
+    #[serde(with = "serde_yaml::with::singleton_map")]
+    pub password: Secret,
+}
+
+/// A value kept out of the configuration file.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "snake_case", deny_unknown_fields)]
+pub enum Secret {
+    /// Read from an environment variable.
+    Env(String),
+    /// Read from a file. A single trailing line break is ignored.
+    File(PathBuf),
+    /// Read from the file of that name handed to the service by its supervisor: in
+    /// `$CREDENTIALS_DIRECTORY` as set up by systemd's `LoadCredential=`, or else in
+    /// `/run/secrets` as mounted by Docker and Kubernetes. A single trailing line break is
+    /// ignored.
+    Credential(String),
+}
+
+/// A TwinCAT Analytics stream, published under `<prefix>/Bin/Tx/...`, or all streams whose
+/// prefix matches a pattern.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct StreamConfig {
+    /// Name of the broker to subscribe on. May be left out if there is only one.
+    pub broker: Option<String>,
+    /// Topic prefix of the stream, e.g. `ema/plc-stream01`. Levels given as `+` match any
+    /// level, so that `+/+` discovers every stream published two levels deep. A stream that
+    /// matches several entries takes the settings of the first. Entries may lie within a
+    /// pattern, but two patterns must not partly overlap.
+    pub prefix: String,
+    #[serde(default = "default_qos")]
+    pub qos: u8,
+    /// Data messages kept while their layout has not been published yet. The oldest are
+    /// dropped first.
+    #[serde(default = "default_max_pending")]
+    pub max_pending: usize,
+    /// Streams a pattern discovers at most. Messages of further streams are ignored, so that
+    /// publishers under ever new prefixes cannot use up memory.
+    #[serde(default = "default_max_streams")]
+    pub max_streams: usize,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ApiConfig {
+    /// Address to serve HTTP on, e.g. `127.0.0.1:8080`.
+    pub listen: SocketAddr,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case", deny_unknown_fields)]
+pub enum SinkConfig {
+    /// JSON lines on standard output.
+    Stdout,
+    /// JSON lines appended to a file.
+    File { path: PathBuf },
+    /// Raw symbol stream payloads, stored as `<directory>/<prefix>/<layout>.symbols`.
+    Archive { directory: PathBuf },
+}
+
+fn default_keep_alive() -> u64 {
+    5
+}
+
+fn default_max_packet_size() -> u32 {
+    16 * 1024 * 1024
+}
+
+fn default_qos() -> u8 {
+    1
+}
+
+fn default_max_pending() -> usize {
+    64
+}
+
+fn default_max_streams() -> usize {
+    256
+}
+
+impl Config {
+    /// Reads the configuration from `path`, as YAML if the file name ends in `.yaml` or `.yml`
+    /// and as TOML otherwise.
+    pub fn load(path: &Path) -> Result<Config, BoxError> {
+        let text = fs::read_to_string(path)
+            .map_err(|e| format!("cannot read config file {}: {e}", path.display()))?;
+        let config: Config = match path.extension().and_then(|e| e.to_str()) {
+            Some("yaml" | "yml") => serde_yaml::from_str(&text)
+                .map_err(|e| format!("invalid config file {}: {e}", path.display()))?,
+            _ => toml::from_str(&text)
+                .map_err(|e| format!("invalid config file {}: {e}", path.display()))?,
+        };
+        config.validate()?;
+        Ok(config)
+    }
+
+    fn validate(&self) -> Result<(), BoxError> {
+        if self.brokers.is_empty() {
+            return Err("no broker configured".into());
+        }
+        for (i, broker) in self.brokers.iter().enumerate() {
+            if self.brokers[..i].iter().any(|b| b.name == broker.name) {
+                return Err(format!("broker {} is configured twice", broker.name).into());
+            }
+            // The port and TLS are configured separately, so a URL is a mistake
+            if broker.host.is_empty() || broker.host.contains(['/', ' ']) {
+                return Err(format!(
+                    "broker {}: invalid host {:?}, expected a host name or address",
+                    broker.name, broker.host
+                )
+                .into());
+            }
+            if let Some(tls) = &broker.tls {
+                if tls.client_cert.is_some() != tls.client_key.is_some() {
+                    return Err(format!(
+                        "broker {}: client_cert and client_key go together",
+                        broker.name
+                    )
+                    .into());
+                }
+            }
+        }
+        for (i, stream) in self.streams.iter().enumerate() {
+            if stream.qos > 2 {
+                return Err(format!("stream {}: invalid QoS {}", stream.prefix, stream.qos).into());
+            }
+            if stream.prefix.is_empty() || stream.prefix.ends_with('/') {
+                return Err(format!("invalid stream prefix {:?}", stream.prefix).into());
+            }
+            // `#` has to be the last level of a filter, and the Bin/Tx topics follow the prefix
+            let wildcard = |level: &str| level.contains(['+', '#']) && level != "+";
+            if stream.prefix.split('/').any(wildcard) {
+                return Err(format!(
+                    "stream prefix {:?}: only whole levels of `+` are allowed as wildcards",
+                    stream.prefix
+                )
+                .into());
+            }
+            let broker = &self.broker_of(stream)?.name;
+            for other in &self.streams[..i] {
+                if &self.broker_of(other)?.name != broker {
+                    continue;
+                }
+                if other.prefix == stream.prefix {
+                    return Err(format!("stream {} is configured twice", stream.prefix).into());
+                }
+                // A stream within a pattern is left to the pattern's subscription, but no single
+                // subscription takes in both of two patterns that only partly overlap
+                if other.overlaps(stream) && !other.covers(stream) && !stream.covers(other) {
+                    return Err(format!(
+                        "stream prefixes {} and {} overlap",
+                        other.prefix, stream.prefix
+                    )
+                    .into());
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// The broker `stream` is published on.
+    pub fn broker_of(&self, stream: &StreamConfig) -> Result<&BrokerConfig, BoxError> {
+        match &stream.broker {
+            Some(name) => self
+                .brokers
+                .iter()
+                .find(|broker| &broker.name == name)
+                .ok_or_else(|| format!("stream {}: unknown broker {name}", stream.prefix).into()),
+            None if self.brokers.len() == 1 => Ok(&self.brokers[0]),
+            None => Err(format!(
+                "stream {}: broker required with several brokers",
+                stream.prefix
+            )
+            .into()),
+        }
+    }
+}
+
+impl Secret {
+    pub fn resolve(&self) -> Result<String, BoxError> {
+        match self {
+            Secret::Env(name) => std::env::var(name)
+                .map_err(|e| format!("cannot read secret from ${name}: {e}").into()),
+            Secret::File(path) => read_secret(path),
+            Secret::Credential(name) => {
+                if name.is_empty() || name.contains('/') || name == "." || name == ".." {
+                    return Err(format!("invalid credential name {name:?}").into());
+                }
+                let directory = std::env::var_os("CREDENTIALS_DIRECTORY")
+                    .map_or_else(|| PathBuf::from("/run/secrets"), PathBuf::from);
+                read_secret(&directory.join(name))
+            }
+        }
+    }
+}
+
+fn read_secret(path: &Path) -> Result<String, BoxError> {
+    let mut secret = fs::read_to_string(path)
+        .map_err(|e| format!("cannot read secret from {}: {e}", path.display()))?;
+    if secret.ends_with('\n') {
+        secret.pop();
+        if secret.ends_with('\r') {
+            secret.pop();
+        }
+    }
+    Ok(secret)
+}
+
+impl StreamConfig {
+    /// Whether the prefix is a pattern rather than the prefix of a single stream.
+    pub fn is_pattern(&self) -> bool {
+        self.prefix.split('/').any(|level| level == "+")
+    }
+
+    /// Whether `prefix` is the stream's prefix or matches its pattern.
+    pub fn matches(&self, prefix: &str) -> bool {
+        let mut levels = prefix.split('/');
+        let mut pattern = self.prefix.split('/');
+        loop {
+            match (pattern.next(), levels.next()) {
+                (None, None) => return true,
+                (Some("+"), Some(_)) => {}
+                (Some(expected), Some(level)) if expected == level => {}
+                _ => return false,
+            }
+        }
+    }
+
+    /// Whether every stream matching `other` matches this stream as well.
+    pub fn covers(&self, other: &StreamConfig) -> bool {
+        // A `+` in the other prefix only matches a `+` here
+        self.matches(&other.prefix)
+    }
+
+    /// Whether some stream matches both this stream and `other`.
+    fn overlaps(&self, other: &StreamConfig) -> bool {
+        let levels = self.prefix.split('/');
+        let other_levels = other.prefix.split('/');
+        levels.clone().count() == other_levels.clone().count()
+            && levels
+                .zip(other_levels)
+                .all(|(a, b)| a == b || a == "+" || b == "+")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(toml: &str) -> Result<Config, BoxError> {
+        let config: Config = toml::from_str(toml)?;
+        config.validate()?;
+        Ok(config)
+    }
+
+    fn error(toml: &str) -> String {
+        parse(toml).unwrap_err().to_string()
+    }
+
+    /// A file in the temporary directory, unique to this process and `name`.
+    fn temp_file(name: &str, contents: &str) -> PathBuf {
+        let path = std::env::temp_dir().join(format!("testbed-{}-{name}", std::process::id()));
+        fs::write(&path, contents).unwrap();
+        path
+    }
+
+    const BROKER: &str = r#"
+        [[brokers]]
+        name = "plant"
+        host = "localhost"
+    "#;
+
+    #[test]
+    fn example() {
+        let config = parse(include_str!("../testbed.example.toml")).unwrap();
+        assert_eq!(config.brokers[0].port, Some(8883));
+        assert!(config.brokers[0].tls.is_some());
+        assert_eq!(config.streams.len(), 2);
+        assert_eq!(config.sinks.len(), 3);
+    }
+
+    #[test]
+    fn defaults() {
+        let config = parse(&format!("{BROKER}\n[[streams]]\nprefix = \"ema/plc\"")).unwrap();
+        let broker = &config.brokers[0];
+        assert_eq!(broker.keep_alive, 5);
+        assert_eq!(broker.max_packet_size, 16 * 1024 * 1024);
+        assert!(broker.tls.is_none());
+        assert_eq!(config.streams[0].qos, 1);
+        assert_eq!(config.streams[0].max_pending, 64);
+        assert_eq!(config.streams[0].max_streams, 256);
+        assert_eq!(config.broker_of(&config.streams[0]).unwrap().name, "plant");
+        assert!(config.api.is_none());
+    }
+
+    #[test]
+    fn yaml() {
+        let config: Config = serde_yaml::from_str(
+            "brokers:\n\
+             - name: plant\n  host: localhost\n  credentials:\n    username: analytics\n    \
+             password:\n      env: MQTT_PASSWORD\n\
+             streams:\n- prefix: ema/plc\n",
+        )
+        .unwrap();
+        config.validate().unwrap();
+        let credentials = config.brokers[0].credentials.as_ref().unwrap();
+        assert!(matches!(&credentials.password, Secret::Env(name) if name == "MQTT_PASSWORD"));
+    }
+
+    #[test]
+    fn brokers() {
+        assert_eq!(error("brokers = []"), "no broker configured");
+        assert_eq!(
+            error(&format!("{BROKER}{BROKER}")),
+            "broker plant is configured twice"
+        );
+        assert!(error(r#"brokers = [{ name = "plant" }]"#).contains("missing field `host`"));
+        assert!(error(&format!("{BROKER}\nqos = 1")).contains("unknown field `qos`"));
+        for host in [
+            "",
+            "mqtts://mqtt.example.net",
+            "mqtt.example.net/",
+            "mqtt example",
+        ] {
+            assert_eq!(
+                error(&format!("[[brokers]]\nname = \"plant\"\nhost = {host:?}")),
+                format!("broker plant: invalid host {host:?}, expected a host name or address")
+            );
+        }
+        parse(r#"brokers = [{ name = "plant", host = "fe80::1" }]"#).unwrap();
+
+        let tls = format!("{BROKER}\n[brokers.tls]\nclient_cert = \"client.pem\"");
+        assert_eq!(
+            error(&tls),
+            "broker plant: client_cert and client_key go together"
+        );
+    }
+
+    #[test]
+    fn streams() {
+        let stream = |prefix: &str| format!("{BROKER}\n[[streams]]\nprefix = {prefix:?}");
+        for prefix in ["", "ema/plc/"] {
+            assert_eq!(
+                error(&stream(prefix)),
+                format!("invalid stream prefix {prefix:?}")
+            );
+        }
+        for prefix in ["ema/#", "ema/plc+", "#"] {
+            assert!(error(&stream(prefix)).contains("only whole levels of `+`"));
+        }
+        parse(&stream("+/+")).unwrap();
+
+        assert_eq!(
+            error(&format!("{}\nqos = 3", stream("ema/plc"))),
+            "stream ema/plc: invalid QoS 3"
+        );
+        assert_eq!(
+            error(&format!(
+                "{}\n[[streams]]\nprefix = \"ema/plc\"",
+                stream("ema/plc")
+            )),
+            "stream ema/plc is configured twice"
+        );
+    }
+
+    #[test]
+    fn matches() {
+        let stream = |prefix: &str| StreamConfig {
+            broker: None,
+            prefix: prefix.to_string(),
+            qos: 1,
+            max_pending: 64,
+            max_streams: 256,
+        };
+        let plc = stream("ema/plc");
+        assert!(!plc.is_pattern());
+        assert!(plc.matches("ema/plc"));
+        assert!(!plc.matches("ema/plc2"));
+        assert!(!plc.matches("ema"));
+        assert!(!plc.matches("ema/plc/x"));
+        assert!(!plc.matches("ema/+"));
+
+        let all = stream("+/+");
+        assert!(all.is_pattern());
+        assert!(all.matches("ema/plc"));
+        assert!(all.matches("a/"));
+        assert!(!all.matches("ema"));
+        assert!(!all.matches("ema/plc/x"));
+        assert!(stream("ema/+/stream").matches("ema/plc/stream"));
+        assert!(!stream("ema/+/stream").matches("ema/plc/other"));
+
+        assert!(all.covers(&plc));
+        assert!(all.covers(&stream("ema/+")));
+        assert!(!plc.covers(&all));
+        assert!(!stream("ema/+").covers(&stream("+/plc")));
+        assert!(stream("ema/+").overlaps(&stream("+/plc")));
+        assert!(!stream("ema/+").overlaps(&stream("+/plc/x")));
+        assert!(!plc.overlaps(&stream("ema/plc2")));
+    }
+
+    #[test]
+    fn overlapping_streams() {
+        let streams = |prefixes: &[&str]| {
+            let streams: String = prefixes
+                .iter()
+                .map(|prefix| format!("\n[[streams]]\nprefix = {prefix:?}"))
+                .collect();
+            format!("{BROKER}{streams}")
+        };
+
+        // A stream within a pattern, to set it up apart from the others
+        parse(&streams(&["ema/plc", "+/+"])).unwrap();
+        parse(&streams(&["+/+", "ema/+", "ema/plc"])).unwrap();
+        parse(&streams(&["ema/+", "ema/+/+"])).unwrap();
+        assert_eq!(
+            error(&streams(&["ema/+", "+/plc"])),
+            "stream prefixes ema/+ and +/plc overlap"
+        );
+        assert_eq!(
+            error(&streams(&["+/+", "ema/+/x", "ema/plc/+"])),
+            "stream prefixes ema/+/x and ema/plc/+ overlap"
+        );
+    }
+
+    #[test]
+    fn stream_brokers() {
+        let two = r#"
+            [[brokers]]
+            name = "a"
+            host = "a.example.net"
+            [[brokers]]
+            name = "b"
+            host = "b.example.net"
+        "#;
+        assert_eq!(
+            error(&format!("{two}\n[[streams]]\nprefix = \"ema/plc\"")),
+            "stream ema/plc: broker required with several brokers"
+        );
+        assert_eq!(
+            error(&format!(
+                "{two}\n[[streams]]\nbroker = \"c\"\nprefix = \"ema/plc\""
+            )),
+            "stream ema/plc: unknown broker c"
+        );
+
+        // The same prefix on two brokers is two streams
+        let config = parse(&format!(
+            "{two}\n[[streams]]\nbroker = \"a\"\nprefix = \"ema/plc\"\n\
+             [[streams]]\nbroker = \"b\"\nprefix = \"ema/plc\""
+        ))
+        .unwrap();
+        assert_eq!(config.broker_of(&config.streams[1]).unwrap().name, "b");
+        parse(&format!(
+            "{two}\n[[streams]]\nbroker = \"a\"\nprefix = \"ema/+\"\n\
+             [[streams]]\nbroker = \"b\"\nprefix = \"+/plc\""
+        ))
+        .unwrap();
+    }
+
+    #[test]
+    fn env_secret() {
+        let name = format!("TESTBED_SECRET_{}", std::process::id());
+        std::env::set_var(&name, "hunter2\n");
+        // Values from the environment are taken as they are
+        assert_eq!(Secret::Env(name.clone()).resolve().unwrap(), "hunter2\n");
+        std::env::remove_var(&name);
+        assert!(Secret::Env(name)
+            .resolve()
+            .unwrap_err()
+            .to_string()
+            .starts_with("cannot read secret from $TESTBED_SECRET_"));
+    }
+
+    #[test]
+    fn file_secret() {
+        for (contents, secret) in [
+            ("hunter2", "hunter2"),
+            ("hunter2\n", "hunter2"),
+            ("hunter2\r\n", "hunter2"),
+            ("hunter2\n\n", "hunter2\n"),
+            (" hunter2 ", " hunter2 "),
+        ] {
+            let path = temp_file("secret", contents);
+            assert_eq!(Secret::File(path.clone()).resolve().unwrap(), secret);
+            fs::remove_file(path).unwrap();
+        }
+
+        let missing = temp_file("missing", "");
+        fs::remove_file(&missing).unwrap();
+        assert!(Secret::File(missing)
+            .resolve()
+            .unwrap_err()
+            .to_string()
+            .starts_with("cannot read secret from "));
+    }
+
+    #[test]
+    fn credential_secret() {
+        let directory = temp_file("credentials", "");
+        fs::remove_file(&directory).unwrap();
+        fs::create_dir(&directory).unwrap();
+        fs::write(directory.join("mqtt-password"), "hunter2\n").unwrap();
+
+        std::env::set_var("CREDENTIALS_DIRECTORY", &directory);
+        let resolved = Secret::Credential("mqtt-password".into()).resolve();
+        std::env::remove_var("CREDENTIALS_DIRECTORY");
+        fs::remove_dir_all(&directory).unwrap();
+        assert_eq!(resolved.unwrap(), "hunter2");
+
+        for name in ["", ".", "..", "../mqtt-password", "a/b"] {
+            assert_eq!(
+                Secret::Credential(name.into())
+                    .resolve()
+                    .unwrap_err()
+                    .to_string(),
+                format!("invalid credential name {name:?}")
+            );
+        }
+    }
+}
@@ -0,0 +1,158 @@
+use std::sync::Arc;
+
+use binary_decoder::{PlcValue, Sample, StreamFlags, SymbolStream};
+use bytes::Bytes;
+use serde_json::{json, Map, Value};
+use uuid::Uuid;
+
+use crate::layout::LayoutDiff;
+
+/// Something that happened on a stream, handed to the sinks.
+#[derive(Debug, Clone)]
+pub enum Event {
+    /// A symbol stream was published and decoded.
+    Symbols {
+        broker: String,
+        prefix: String,
+        payload: Bytes,
+        symbol_stream: Arc<SymbolStream>,
+    },
+    /// A stream moved to a new layout, after an online change or a download. Data is decoded
+    /// with the new layout from here on.
+    LayoutChanged {
+        broker: String,
+        prefix: String,
+        previous: Uuid,
+        layout: Uuid,
+        /// Whether the symbol stream header marks the change as an online change.
+        online_change: bool,
+        diff: LayoutDiff,
+    },
+    /// A data stream was decoded with the layout of the stream.
+    Samples {
+        broker: String,
+        prefix: String,
+        layout: Uuid,
+        samples: Vec<Sample>,
+    },
+}
+
+impl Event {
+    pub fn prefix(&self) -> &str {
+        match self {
+            Event::Symbols { prefix, .. }
+            | Event::LayoutChanged { prefix, .. }
+            | Event::Samples { prefix, .. } => prefix,
+        }
+    }
+
+    /// The event as one line of JSON for the log sinks. Raw payloads are left out.
+    pub fn to_json(&self) -> Value {
+        match self {
+            Event::Symbols {
+                broker,
+                prefix,
+                symbol_stream,
+                ..
+            } => {
+                let header = &symbol_stream.header;
+                json!({
+                    "event": "symbols",
+                    "broker": broker,
+                    "prefix": prefix,
+                    "layout": header.layout.to_string(),
+                    "version": header.version.to_string(),
+                    "code_page": header.code_page,
+                    "online_change": header.flags.contains(StreamFlags::ONLINE_CHANGE),
+                    "symbols": symbol_stream.symbols.len(),
+                    "data_types": symbol_stream.data_types.len(),
+                })
+            }
+            Event::LayoutChanged {
+                broker,
+                prefix,
+                previous,
+                layout,
+                online_change,
+                diff,
+            } => {
+                let retyped: Vec<Value> = diff
+                    .retyped
+                    .iter()
+                    .map(|r| json!({"name": r.name, "from": r.from, "to": r.to}))
+                    .collect();
+                json!({
+                    "event": "layout_changed",
+                    "broker": broker,
+                    "prefix": prefix,
+                    "previous": previous.to_string(),
+                    "layout": layout.to_string(),
+                    "online_change": online_change,
+                    "added": diff.added,
+                    "removed": diff.removed,
+                    "retyped": retyped,
+                })
+            }
+            Event::Samples {
+                broker,
+                prefix,
+                layout,
+                samples,
+            } => {
+                let samples: Vec<Value> = samples
+                    .iter()
+                    .map(|sample| {
+                        let values: Map<String, Value> = sample
+                            .values
+                            .iter()
+                            .map(|v| (v.name.to_string(), value_json(&v.value)))
+                            .collect();
+                        json!({
+                            "timestamp": sample.timestamp.map(|t| t.to_string()),
+                            "values": values,
+                        })
+                    })
+                    .collect();
+                json!({
+                    "event": "samples",
+                    "broker": broker,
+                    "prefix": prefix,
+                    "layout": layout.to_string(),
+                    "samples": samples,
+                })
+            }
+        }
+    }
+}
+
+/// A PLC value in JSON. Enumerations are given by name where they have one, raw bytes as hex.
+fn value_json(value: &PlcValue) -> Value {
+    match value {
+        PlcValue::Bool(v) => json!(v),
+        PlcValue::SInt(v) => json!(v),
+        PlcValue::USInt(v) => json!(v),
+        PlcValue::Int(v) => json!(v),
+        PlcValue::UInt(v) => json!(v),
+        PlcValue::DInt(v) => json!(v),
+        PlcValue::UDInt(v) => json!(v),
+        PlcValue::LInt(v) => json!(v),
+        PlcValue::ULInt(v) => json!(v),
+        PlcValue::Real(v) => json!(v),
+        PlcValue::LReal(v) | PlcValue::Real80(v) => json!(v),
+        PlcValue::String(v) | PlcValue::WString(v) => json!(v),
+        PlcValue::Struct(members) => Value::Object(
+            members
+                .iter()
+                .map(|(name, value)| (name.clone(), value_json(value)))
+                .collect(),
+        ),
+        PlcValue::Array(elements) => Value::Array(elements.iter().map(value_json).collect()),
+        PlcValue::Enum {
+            name: Some(name), ..
+        } => json!(name),
+        PlcValue::Enum { value, .. } => json!(value),
+        PlcValue::Bytes(bytes) => {
+            json!(bytes.iter().map(|b| format!("{b:02x}")).collect::<String>())
+        }
+    }
+}
@@ -0,0 +1,8 @@
+//! Symbol streams shared by the unit tests.
+
+use binary_decoder::SymbolStream;
+
+/// The symbol stream the decoder is tested with, as a PLC publishes it.
+pub fn ema() -> SymbolStream {
+    SymbolStream::parse(include_bytes!("../../binary-decoder/ema.symbol_stream")).unwrap()
+}
@@ -0,0 +1,387 @@
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::Duration;
+
+use binary_decoder::{DataStream, SymbolStream, TextMode};
+use bytes::Bytes;
+use log::{debug, info, warn};
+use rumqttc::v5::mqttbytes::qos;
+use rumqttc::v5::mqttbytes::v5::{Filter, Packet, Publish};
+use rumqttc::v5::{AsyncClient, Event as MqttEvent, EventLoop, MqttOptions};
+use rumqttc::{TlsConfiguration, Transport};
+use tokio::sync::mpsc::{self, error::TrySendError};
+use tokio::time;
+
+use crate::config::{BrokerConfig, StreamConfig};
+use crate::event::Event;
+use crate::registry::Registry;
+use crate::stream::Stream;
+use crate::tls;
+use crate::BoxError;
+
+const SYMBOLS_TOPIC: &str = "/Bin/Tx/Symbols";
+const DATA_TOPIC: &str = "/Bin/Tx/Data";
+
+/// Longest wait between two connection attempts.
+const MAX_RECONNECT_DELAY: Duration = Duration::from_secs(60);
+
+/// Subscribes to the streams published on one broker and turns what they publish into events.
+pub struct Ingest {
+    broker: String,
+    client: AsyncClient,
+    eventloop: EventLoop,
+    configs: Vec<StreamConfig>,
+    /// Streams discovered so far by each of `configs`.
+    discovered: Vec<usize>,
+    /// State of the streams seen so far, by topic prefix.
+    streams: HashMap<String, Stream>,
+    registry: Registry,
+    events: mpsc::Sender<Event>,
+    /// Data messages dropped since the sinks last kept up.
+    dropped: usize,
+}
+
+impl Ingest {
+    pub fn new(
+        broker: &BrokerConfig,
+        streams: Vec<StreamConfig>,
+        registry: Registry,
+        events: mpsc::Sender<Event>,
+    ) -> Result<Ingest, BoxError> {
+        let (client, eventloop) = AsyncClient::new(mqtt_options(broker)?, 10);
+        Ok(Ingest {
+            broker: broker.name.clone(),
+            client,
+            eventloop,
+            discovered: vec![0; streams.len()],
+            configs: streams,
+            streams: HashMap::new(),
+            registry,
+            events,
+            dropped: 0,
+        })
+    }
+
+    /// Runs until the sinks go away, reconnecting whenever the connection is lost.
+    pub async fn run(mut self) {
+        let mut delay = Duration::from_secs(1);
+        loop {
+            match self.eventloop.poll().await {
+                Ok(MqttEvent::Incoming(Packet::ConnAck(_))) => {
+                    info!("{}: connected", self.broker);
+                    delay = Duration::from_secs(1);
+                    // Subscriptions do not outlive a clean session
+                    self.subscribe();
+                }
+                Ok(MqttEvent::Incoming(Packet::Publish(publish))) => {
+                    for event in self.decode(publish) {
+                        if !self.deliver(event).await {
+                            return;
+                        }
+                    }
+                }
+                Ok(event) => debug!("{}: {event:?}", self.broker),
+                Err(e) => {
+                    warn!("{}: {e}, reconnecting in {delay:?}", self.broker);
+                    time::sleep(delay).await;
+                    delay = (delay * 2).min(MAX_RECONNECT_DELAY);
+                }
+            }
+        }
+    }
+
+    /// Hands `event` to the sinks. Samples are dropped if the sinks are behind: waiting for
+    /// them here would stop the event loop, and with it the keep-alive of the connection. Symbol
+    /// streams and layout changes are rare and needed to make sense of what follows, so they
+    /// wait for room instead. Returns whether the sinks are still there.
+    async fn deliver(&mut self, event: Event) -> bool {
+        if !matches!(event, Event::Samples { .. }) {
+            return self.events.send(event).await.is_ok();
+        }
+        match self.events.try_send(event) {
+            Ok(()) => {
+                if self.dropped > 0 {
+                    warn!(
+                        "{}: sinks caught up, {} data messages dropped",
+                        self.broker, self.dropped
+                    );
+                    self.dropped = 0;
+                }
+                true
+            }
+            Err(TrySendError::Full(_)) => {
+                if self.dropped == 0 {
+                    warn!("{}: sinks are behind, dropping data", self.broker);
+                }
+                self.dropped += 1;
+                true
+            }
+            Err(TrySendError::Closed(_)) => false,
+        }
+    }
+
+    fn subscribe(&self) {
+        if self.configs.is_empty() {
+            return;
+        }
+        let filters = subscriptions(&self.configs)
+            .into_iter()
+            .flat_map(|(prefix, qos_level)| {
+                let qos = qos(qos_level).expect("QoS is validated with the config");
+                [SYMBOLS_TOPIC, DATA_TOPIC]
+                    .map(|topic| Filter::new(format!("{prefix}{topic}"), qos))
+            });
+        // Not awaited: the request is only sent once the event loop is polled again
+        if let Err(e) = self.client.try_subscribe_many(filters) {
+            warn!("{}: cannot subscribe: {e}", self.broker);
+        }
+    }
+
+    /// Turns a message into events, or logs why it cannot.
+    fn decode(&mut self, publish: Publish) -> Vec<Event> {
+        let topic = String::from_utf8_lossy(&publish.topic);
+        let (prefix, is_symbols) = if let Some(prefix) = topic.strip_suffix(SYMBOLS_TOPIC) {
+            (prefix, true)
+        } else if let Some(prefix) = topic.strip_suffix(DATA_TOPIC) {
+            (prefix, false)
+        } else {
+            debug!("{}: ignoring message on {topic}", self.broker);
+            return vec![];
+        };
+        if !self.discover(prefix) {
+            debug!("{}: ignoring message on {topic}", self.broker);
+            return vec![];
+        }
+        let stream = self.streams.get_mut(prefix).expect("discovered stream");
+
+        let events = if is_symbols {
+            symbols(stream, prefix, publish.payload)
+        } else {
+            match DataStream::parse(&publish.payload) {
+                Ok(data) => stream.data(data).into_iter().collect(),
+                Err(e) => {
+                    warn!("{prefix}: cannot decode data stream: {e}");
+                    vec![]
+                }
+            }
+        };
+        self.registry
+            .seen(&self.broker, prefix, stream.symbol_stream());
+        events
+    }
+
+    /// Sets up the state of the stream `prefix` on its first message. Returns whether the
+    /// stream is configured or matches a configured pattern that has room for it.
+    fn discover(&mut self, prefix: &str) -> bool {
+        if self.streams.contains_key(prefix) {
+            return true;
+        }
+        let Some(index) = self
+            .configs
+            .iter()
+            .position(|config| config.matches(prefix))
+        else {
+            return false;
+        };
+        let config = &self.configs[index];
+        if config.is_pattern() {
+            if self.discovered[index] == config.max_streams {
+                return false;
+            }
+            self.discovered[index] += 1;
+            info!("{}: discovered stream {prefix}", self.broker);
+            if self.discovered[index] == config.max_streams {
+                warn!(
+                    "{}: {} streams discovered by {}, ignoring any further ones",
+                    self.broker, config.max_streams, config.prefix
+                );
+            }
+        }
+        let stream = Stream::new(&self.broker, prefix, config.max_pending);
+        self.streams.insert(prefix.to_string(), stream);
+        true
+    }
+}
+
+/// The prefixes to subscribe to, with their QoS. A broker may deliver a message once for every
+/// subscription it matches, so streams that lie within a pattern are left to the pattern's
+/// subscription, at the highest QoS of the streams it takes in.
+fn subscriptions(configs: &[StreamConfig]) -> Vec<(&str, u8)> {
+    configs
+        .iter()
+        .filter(|stream| {
+            !configs
+                .iter()
+                .any(|other| other.prefix != stream.prefix && other.covers(stream))
+        })
+        .map(|pattern| {
+            let qos = configs
+                .iter()
+                .filter(|stream| pattern.covers(stream))
+                .map(|stream| stream.qos)
+                .max()
+                .unwrap_or(pattern.qos);
+            (pattern.prefix.as_str(), qos)
+        })
+        .collect()
+}
+
+fn symbols(stream: &mut Stream, prefix: &str, payload: Bytes) -> Vec<Event> {
+    // Names that are not valid in the stream's code page should not cost us the stream
+    let symbol_stream = match SymbolStream::parse_with(&payload, TextMode::Lossy) {
+        Ok(symbol_stream) => symbol_stream,
+        Err(e) => {
+            warn!("{prefix}: cannot decode symbol stream: {e}");
+            return vec![];
+        }
+    };
+    info!(
+        "{prefix}: layout {} with {} symbols",
+        symbol_stream.header.layout,
+        symbol_stream.symbols.len()
+    );
+    stream.symbols(payload, symbol_stream)
+}
+
+fn mqtt_options(broker: &BrokerConfig) -> Result<MqttOptions, BoxError> {
+    let client_id = broker
+        .client_id
+        .clone()
+        .unwrap_or_else(|| format!("analytics-ingest-{}", std::process::id()));
+    let port = broker
+        .port
+        .unwrap_or(if broker.tls.is_some() { 8883 } else { 1883 });
+    let mut options = MqttOptions::new(client_id, &broker.host, port);
+    options
+        .set_keep_alive(Duration::from_secs(broker.keep_alive))
+        .set_max_packet_size(Some(broker.max_packet_size));
+    if let Some(credentials) = &broker.credentials {
+        let password = credentials
+            .password
+            .resolve()
+            .map_err(|e| format!("broker {}: {e}", broker.name))?;
+        options.set_credentials(&credentials.username, password);
+    }
+    if let Some(tls) = &broker.tls {
+        let config = tls::client_config(tls).map_err(|e| format!("broker {}: {e}", broker.name))?;
+        options.set_transport(Transport::tls_with_config(TlsConfiguration::Rustls(
+            Arc::new(config),
+        )));
+    }
+    Ok(options)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fixtures::ema;
+
+    fn ingest(configs: Vec<StreamConfig>, events: mpsc::Sender<Event>) -> Ingest {
+        let broker = toml::from_str("name = \"plant\"\nhost = \"localhost\"").unwrap();
+        Ingest::new(&broker, configs, Registry::default(), events).unwrap()
+    }
+
+    fn samples() -> Event {
+        Event::Samples {
+            broker: "plant".to_string(),
+            prefix: "ema/plc".to_string(),
+            layout: ema().header.layout,
+            samples: vec![],
+        }
+    }
+
+    fn symbols() -> Event {
+        Event::Symbols {
+            broker: "plant".to_string(),
+            prefix: "ema/plc".to_string(),
+            payload: Bytes::new(),
+            symbol_stream: Arc::new(ema()),
+        }
+    }
+
+    fn stream(prefix: &str, qos: u8) -> StreamConfig {
+        StreamConfig {
+            broker: None,
+            prefix: prefix.to_string(),
+            qos,
+            max_pending: 64,
+            max_streams: 256,
+        }
+    }
+
+    #[tokio::test]
+    async fn deliver_when_behind() {
+        let (events, mut receiver) = mpsc::channel(1);
+        let mut ingest = ingest(vec![], events);
+        assert!(ingest.deliver(samples()).await);
+
+        // Samples are dropped while the sinks are behind
+        assert!(ingest.deliver(samples()).await);
+        assert_eq!(ingest.dropped, 1);
+
+        // A symbol stream waits for the sinks instead
+        let (delivered, received) = tokio::join!(ingest.deliver(symbols()), async {
+            [receiver.recv().await, receiver.recv().await]
+        });
+        assert!(delivered);
+        assert!(matches!(
+            received,
+            [Some(Event::Samples { .. }), Some(Event::Symbols { .. })]
+        ));
+
+        assert!(ingest.deliver(samples()).await);
+        assert_eq!(ingest.dropped, 0);
+
+        drop(receiver);
+        assert!(!ingest.deliver(samples()).await);
+        assert!(!ingest.deliver(symbols()).await);
+    }
+
+    #[test]
+    fn max_streams() {
+        let configs = vec![
+            StreamConfig {
+                max_streams: 2,
+                ..stream("ema/+", 1)
+            },
+            stream("lab/plc", 1),
+        ];
+        let (events, _receiver) = mpsc::channel(1);
+        let mut ingest = ingest(configs, events);
+
+        assert!(ingest.discover("ema/plc1"));
+        assert!(ingest.discover("ema/plc2"));
+        assert!(!ingest.discover("ema/plc3"));
+        // Streams discovered before keep going, and other entries are not affected
+        assert!(ingest.discover("ema/plc1"));
+        assert!(ingest.discover("lab/plc"));
+        assert!(!ingest.discover("lab/other"));
+        assert_eq!(ingest.streams.len(), 3);
+    }
+
+    #[test]
+    fn separate_subscriptions() {
+        let configs = [
+            stream("ema/plc1", 1),
+            stream("ema/plc2", 0),
+            stream("+/+/x", 2),
+        ];
+        assert_eq!(
+            subscriptions(&configs),
+            [("ema/plc1", 1), ("ema/plc2", 0), ("+/+/x", 2)]
+        );
+    }
+
+    #[test]
+    fn overlapping_subscriptions() {
+        // Each message is subscribed to once, at the highest QoS wanted for it
+        let configs = [
+            stream("ema/plc1", 2),
+            stream("ema/+", 0),
+            stream("+/+", 1),
+            stream("lab/plc", 0),
+            stream("ema/+/x", 0),
+        ];
+        assert_eq!(subscriptions(&configs), [("+/+", 2), ("ema/+/x", 0)]);
+    }
+}
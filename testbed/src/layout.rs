@@ -0,0 +1,133 @@
+use std::collections::HashMap;
+
+use binary_decoder::{Symbol, SymbolStream};
+
+/// How the symbols of a layout differ from those of the layout before it.
+#[derive(Debug, Clone, Default)]
+pub struct LayoutDiff {
+    /// Symbols only in the new layout, in its order.
+    pub added: Vec<String>,
+    /// Symbols only in the old layout, in its order.
+    pub removed: Vec<String>,
+    /// Symbols in both layouts whose type changed, in the order of the new layout.
+    pub retyped: Vec<Retyped>,
+}
+
+#[derive(Debug, Clone)]
+pub struct Retyped {
+    pub name: String,
+    pub from: String,
+    pub to: String,
+}
+
+impl LayoutDiff {
+    /// Compares the symbols of two layouts by name.
+    pub fn new(old: &SymbolStream, new: &SymbolStream) -> LayoutDiff {
+        let old_symbols: HashMap<&str, &Symbol> =
+            old.symbols.iter().map(|s| (s.name.as_str(), s)).collect();
+        let new_symbols: HashMap<&str, &Symbol> =
+            new.symbols.iter().map(|s| (s.name.as_str(), s)).collect();
+
+        let mut diff = LayoutDiff::default();
+        for symbol in &new.symbols {
+            match old_symbols.get(symbol.name.as_str()) {
+                None => diff.added.push(symbol.name.clone()),
+                Some(old) if retyped(old, symbol) => diff.retyped.push(Retyped {
+                    name: symbol.name.clone(),
+                    from: old.data_type_name.clone(),
+                    to: symbol.data_type_name.clone(),
+                }),
+                Some(_) => {}
+            }
+        }
+        diff.removed = old
+            .symbols
+            .iter()
+            .filter(|s| !new_symbols.contains_key(s.name.as_str()))
+            .map(|s| s.name.clone())
+            .collect();
+        diff
+    }
+}
+
+/// A type keeps its name across an online change that adds a member, so sizes and type GUIDs
+/// are compared as well.
+fn retyped(old: &Symbol, new: &Symbol) -> bool {
+    old.data_type_name != new.data_type_name
+        || old.data_type != new.data_type
+        || old.len != new.len
+        || old.data_type_guid != new.data_type_guid
+}
+
+#[cfg(test)]
+mod tests {
+    use uuid::Uuid;
+
+    use super::*;
+    use crate::fixtures::ema;
+
+    fn symbol<'a>(symbol_stream: &'a mut SymbolStream, name: &str) -> &'a mut Symbol {
+        symbol_stream
+            .symbols
+            .iter_mut()
+            .find(|symbol| symbol.name == name)
+            .unwrap()
+    }
+
+    fn retyped_names(diff: &LayoutDiff) -> Vec<&str> {
+        diff.retyped.iter().map(|r| r.name.as_str()).collect()
+    }
+
+    #[test]
+    fn unchanged() {
+        let diff = LayoutDiff::new(&ema(), &ema());
+        assert!(diff.added.is_empty());
+        assert!(diff.removed.is_empty());
+        assert!(diff.retyped.is_empty());
+    }
+
+    #[test]
+    fn added_and_removed() {
+        let old = ema();
+        let mut new = ema();
+        new.symbols
+            .retain(|symbol| symbol.name != "Main.b_sintValue" && symbol.name != "Main.d_intValue");
+        let mut added = new.symbols[0].clone();
+        added.name = "Main.z_newValue".into();
+        new.symbols.insert(1, added);
+
+        let diff = LayoutDiff::new(&old, &new);
+        assert_eq!(diff.added, ["Main.z_newValue"]);
+        assert_eq!(diff.removed, ["Main.b_sintValue", "Main.d_intValue"]);
+        assert!(diff.retyped.is_empty());
+
+        // The other way round
+        let diff = LayoutDiff::new(&new, &old);
+        assert_eq!(diff.added, ["Main.b_sintValue", "Main.d_intValue"]);
+        assert_eq!(diff.removed, ["Main.z_newValue"]);
+    }
+
+    #[test]
+    fn retyped() {
+        let old = ema();
+        let mut new = ema();
+        let int = symbol(&mut new, "Main.d_intValue");
+        int.data_type_name = "DINT".into();
+        int.len = 4;
+        // A struct that gained a member keeps its name
+        symbol(&mut new, "Main.r_structValue").len += 2;
+        symbol(&mut new, "Main.t_enumValue").data_type_guid = Some(Uuid::from_u128(1));
+
+        let diff = LayoutDiff::new(&old, &new);
+        assert!(diff.added.is_empty());
+        assert!(diff.removed.is_empty());
+        assert_eq!(
+            retyped_names(&diff),
+            ["Main.d_intValue", "Main.r_structValue", "Main.t_enumValue"]
+        );
+        let int = &diff.retyped[0];
+        assert_eq!((int.from.as_str(), int.to.as_str()), ("INT", "DINT"));
+        let test_struct = &diff.retyped[1];
+        assert_eq!(test_struct.from, test_struct.to);
+    }
+}
@@ -1,60 +1,97 @@
-use rumqttc::v5::mqttbytes::QoS;
-use rumqttc::v5::mqttbytes::v5::Packet;
-use tokio::{task, time};
+//! Ingests TwinCAT Analytics streams from MQTT brokers, as configured in a TOML or YAML file
+//! given as the only argument (default `testbed.toml`).
+
+mod api;
+mod config;
+mod event;
+#[cfg(test)]
+mod fixtures;
+mod ingest;
+mod layout;
+mod registry;
+mod sink;
+mod stream;
+mod tls;
 
-use rumqttc::v5::{AsyncClient, MqttOptions, Event};
 use std::error::Error;
-use std::fs::File;
-use std::io::Write;
-use std::time::Duration;
+use std::path::PathBuf;
+
+use log::{error, info};
+use tokio::net::TcpListener;
+use tokio::signal;
+use tokio::sync::mpsc;
+
+use crate::config::{Config, SinkConfig};
+use crate::ingest::Ingest;
+use crate::registry::Registry;
+use crate::sink::Sink;
+
+pub type BoxError = Box<dyn Error + Send + Sync>;
+
+/// Events waiting for the sinks. Ingestion drops samples rather than wait for the sinks.
+const EVENT_BUFFER: usize = 1024;
 
-#[tokio::main(worker_threads = 1)]
-async fn main() -> Result<(), Box<dyn Error>> {
+#[tokio::main]
+async fn main() {
     pretty_env_logger::init();
-    // color_backtrace::install();
-
-    let mut mqttoptions = MqttOptions::new("test-1", "mqtt01.klipspringer.inductabend.net", 1883);
-    mqttoptions
-        .set_keep_alive(Duration::from_secs(5))
-        .set_credentials("test", "SAeHZdsuZd7XJTTPAbR2vXD3p7FzjzCY");
-
-    let (client, mut eventloop) = AsyncClient::new(mqttoptions, 10);
-    task::spawn(async move {
-        requests(client).await;
-        time::sleep(Duration::from_secs(3)).await;
-    });
-
-    loop {
-        let event = eventloop.poll().await;
-        match &event {
-            Ok(v) => {
-                println!("Event = {v:?}");
-                match &v {
-                    Event::Incoming(p) => {
-                        match p {
-                            Packet::Publish(p)=>{
-                                let mut file = File::create("symbols.bin")?;
-                                file.write_all(&p.payload)?;
-                            },
-                            _ => {},
-                        }
-                    },
-                    _ => {},
-                }
+
+    if let Err(e) = run().await {
+        eprintln!("error: {e}");
+        std::process::exit(1);
+    }
+}
+
+async fn run() -> Result<(), BoxError> {
+    let path = std::env::args_os()
+        .nth(1)
+        .map_or_else(|| PathBuf::from("testbed.toml"), PathBuf::from);
+    let config = Config::load(&path)?;
+
+    let mut sink_configs = config.sinks.clone();
+    if sink_configs.is_empty() {
+        sink_configs.push(SinkConfig::Stdout);
+    }
+    let mut sinks = vec![];
+    for sink_config in &sink_configs {
+        let sink = Sink::open(sink_config)
+            .await
+            .map_err(|e| format!("cannot open sink {sink_config:?}: {e}"))?;
+        sinks.push(sink);
+    }
+
+    let registry = Registry::default();
+    if let Some(api) = &config.api {
+        let listener = TcpListener::bind(api.listen)
+            .await
+            .map_err(|e| format!("cannot serve API on {}: {e}", api.listen))?;
+        info!("serving API on {}", api.listen);
+        let registry = registry.clone();
+        tokio::spawn(async move {
+            if let Err(e) = api::serve(listener, registry).await {
+                error!("API stopped: {e}");
             }
-            Err(e) => {
-                println!("Error = {e:?}");
-                return Ok(());
+        });
+    }
+
+    let (events, receiver) = mpsc::channel(EVENT_BUFFER);
+    for broker in &config.brokers {
+        let mut streams = vec![];
+        for stream in &config.streams {
+            if config.broker_of(stream)?.name == broker.name {
+                streams.push(stream.clone());
             }
         }
+        let ingest = Ingest::new(broker, streams, registry.clone(), events.clone())?;
+        tokio::spawn(ingest.run());
     }
-}
-
-async fn requests(client: AsyncClient) {
-    client
-        .subscribe("ema/plc-stream01/Bin/Tx/Symbols", QoS::AtMostOnce)
-        .await
-        .unwrap();
+    drop(events);
 
-    time::sleep(Duration::from_secs(120)).await;
+    tokio::select! {
+        _ = sink::run(sinks, receiver) => {}
+        result = signal::ctrl_c() => {
+            result?;
+            info!("shutting down");
+        }
+    }
+    Ok(())
 }
@@ -0,0 +1,134 @@
+use std::collections::BTreeMap;
+use std::sync::{Arc, Mutex};
+use std::time::SystemTime;
+
+use binary_decoder::SymbolStream;
+use serde_json::{json, Value};
+use uuid::Uuid;
+
+/// The streams seen on the brokers, shared between the ingestion tasks and the API.
+#[derive(Clone, Default)]
+pub struct Registry {
+    /// By broker and prefix.
+    streams: Arc<Mutex<BTreeMap<(String, String), StreamInfo>>>,
+}
+
+#[derive(Debug, Clone)]
+pub struct StreamInfo {
+    pub broker: String,
+    pub prefix: String,
+    /// Layout of the latest symbol stream, `None` until one is received.
+    pub layout: Option<Uuid>,
+    pub symbols: usize,
+    /// When a message was last received on the stream.
+    pub last_seen: SystemTime,
+}
+
+impl Registry {
+    /// Records a message on the stream `prefix`, which now has the layout of `symbol_stream`.
+    pub fn seen(&self, broker: &str, prefix: &str, symbol_stream: Option<&SymbolStream>) {
+        let info = StreamInfo {
+            broker: broker.to_string(),
+            prefix: prefix.to_string(),
+            layout: symbol_stream.map(|s| s.header.layout),
+            symbols: symbol_stream.map_or(0, |s| s.symbols.len()),
+            last_seen: SystemTime::now(),
+        };
+        let mut streams = self.streams.lock().expect("registry lock");
+        streams.insert((info.broker.clone(), info.prefix.clone()), info);
+    }
+
+    /// Every stream seen so far, by broker and prefix.
+    pub fn streams(&self) -> Vec<StreamInfo> {
+        let streams = self.streams.lock().expect("registry lock");
+        streams.values().cloned().collect()
+    }
+}
+
+impl StreamInfo {
+    pub fn to_json(&self) -> Value {
+        json!({
+            "broker": self.broker,
+            "prefix": self.prefix,
+            "layout": self.layout.map(|layout| layout.to_string()),
+            "symbols": self.symbols,
+            "last_seen": humantime::format_rfc3339_nanos(self.last_seen).to_string(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::*;
+    use crate::fixtures::ema;
+
+    #[test]
+    fn streams() {
+        let registry = Registry::default();
+        assert!(registry.streams().is_empty());
+
+        let before = SystemTime::now();
+        registry.seen("plant", "ema/plc2", None);
+        registry.seen("plant", "ema/plc1", None);
+        registry.seen("lab", "ema/plc1", None);
+
+        // By broker and prefix
+        let streams = registry.streams();
+        let keys: Vec<_> = streams
+            .iter()
+            .map(|s| (s.broker.as_str(), s.prefix.as_str()))
+            .collect();
+        assert_eq!(
+            keys,
+            [
+                ("lab", "ema/plc1"),
+                ("plant", "ema/plc1"),
+                ("plant", "ema/plc2")
+            ]
+        );
+        for stream in &streams {
+            assert_eq!(stream.layout, None);
+            assert_eq!(stream.symbols, 0);
+            assert!(stream.last_seen >= before && stream.last_seen <= SystemTime::now());
+        }
+
+        // A later message updates the stream
+        let symbol_stream = ema();
+        registry.seen("plant", "ema/plc1", Some(&symbol_stream));
+        let streams = registry.streams();
+        assert_eq!(streams.len(), 3);
+        assert_eq!(streams[1].layout, Some(symbol_stream.header.layout));
+        assert_eq!(streams[1].symbols, symbol_stream.symbols.len());
+        assert!(streams[1].last_seen >= streams[0].last_seen);
+
+        // Clones share the streams
+        let clone = registry.clone();
+        clone.seen("plant", "ema/plc3", None);
+        assert_eq!(registry.streams().len(), 4);
+    }
+
+    #[test]
+    fn to_json() {
+        let info = StreamInfo {
+            broker: "plant".into(),
+            prefix: "ema/plc".into(),
+            layout: None,
+            symbols: 0,
+            last_seen: SystemTime::UNIX_EPOCH + Duration::new(1_700_000_000, 5),
+        };
+        assert_eq!(
+            info.to_json().to_string(),
+            r#"{"broker":"plant","prefix":"ema/plc","layout":null,"symbols":0,"last_seen":"2023-11-14T22:13:20.000000005Z"}"#
+        );
+
+        let layout = ema().header.layout;
+        let info = StreamInfo {
+            layout: Some(layout),
+            symbols: 20,
+            ..info
+        };
+        assert_eq!(info.to_json()["layout"], layout.to_string());
+    }
+}
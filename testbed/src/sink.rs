@@ -0,0 +1,101 @@
+use std::io;
+use std::path::{Path, PathBuf};
+
+use log::warn;
+use tokio::fs::{self, File, OpenOptions};
+use tokio::io::{AsyncWriteExt, BufWriter, Stdout};
+use tokio::sync::mpsc;
+
+use crate::config::SinkConfig;
+use crate::event::Event;
+
+/// An output for events.
+pub enum Sink {
+    Stdout(Stdout),
+    File(BufWriter<File>),
+    Archive(PathBuf),
+}
+
+impl Sink {
+    pub async fn open(config: &SinkConfig) -> io::Result<Sink> {
+        let sink = match config {
+            SinkConfig::Stdout => Sink::Stdout(tokio::io::stdout()),
+            SinkConfig::File { path } => {
+                let file = OpenOptions::new()
+                    .create(true)
+                    .append(true)
+                    .open(path)
+                    .await?;
+                Sink::File(BufWriter::new(file))
+            }
+            SinkConfig::Archive { directory } => {
+                fs::create_dir_all(directory).await?;
+                Sink::Archive(directory.clone())
+            }
+        };
+        Ok(sink)
+    }
+
+    pub async fn write(&mut self, event: &Event) -> io::Result<()> {
+        match self {
+            Sink::Stdout(out) => write_line(out, event).await,
+            Sink::File(out) => write_line(out, event).await,
+            Sink::Archive(directory) => archive(directory, event).await,
+        }
+    }
+}
+
+/// Hands every event to every sink until all senders are gone. A failing sink is reported and
+/// does not stop the others.
+pub async fn run(mut sinks: Vec<Sink>, mut events: mpsc::Receiver<Event>) {
+    while let Some(event) = events.recv().await {
+        for sink in &mut sinks {
+            if let Err(e) = sink.write(&event).await {
+                warn!("{}: cannot write event: {e}", event.prefix());
+            }
+        }
+    }
+}
+
+async fn write_line<W>(out: &mut W, event: &Event) -> io::Result<()>
+where
+    W: AsyncWriteExt + Unpin,
+{
+    let mut line = event.to_json().to_string();
+    line.push('\n');
+    out.write_all(line.as_bytes()).await?;
+    out.flush().await
+}
+
+/// Stores the payload of a symbol stream once per layout. Other events are not archived.
+async fn archive(directory: &Path, event: &Event) -> io::Result<()> {
+    let Event::Symbols {
+        prefix,
+        payload,
+        symbol_stream,
+        ..
+    } = event
+    else {
+        return Ok(());
+    };
+
+    let directory = directory.join(prefix_path(prefix));
+    let path = directory.join(format!("{}.symbols", symbol_stream.header.layout));
+    if fs::try_exists(&path).await? {
+        return Ok(());
+    }
+    fs::create_dir_all(&directory).await?;
+    fs::write(path, payload).await
+}
+
+/// Maps a topic prefix to a relative path, one directory per topic level. Levels that would
+/// escape the directory are replaced.
+fn prefix_path(prefix: &str) -> PathBuf {
+    prefix
+        .split('/')
+        .map(|level| match level {
+            "" | "." | ".." => "_",
+            level => level,
+        })
+        .collect()
+}
@@ -0,0 +1,309 @@
+use std::collections::VecDeque;
+use std::sync::Arc;
+
+use binary_decoder::{DataStream, SampleDecoder, StreamFlags, SymbolStream};
+use bytes::Bytes;
+use log::{debug, info, warn};
+use uuid::Uuid;
+
+use crate::event::Event;
+use crate::layout::LayoutDiff;
+
+/// Layouts kept per stream, so that data published just before an online change still decodes
+/// once the new layout is in place.
+const MAX_LAYOUTS: usize = 8;
+
+/// The state of one Analytics stream: its recent layouts and the data waiting for a layout.
+pub struct Stream {
+    broker: String,
+    prefix: String,
+    max_pending: usize,
+    /// Most recent layout last.
+    layouts: VecDeque<Layout>,
+    pending: VecDeque<DataStream>,
+}
+
+/// A symbol stream with the decoder for the samples laid out by it.
+struct Layout {
+    symbol_stream: Arc<SymbolStream>,
+    decoder: SampleDecoder,
+}
+
+impl Stream {
+    pub fn new(broker: &str, prefix: &str, max_pending: usize) -> Stream {
+        Stream {
+            broker: broker.to_string(),
+            prefix: prefix.to_string(),
+            max_pending,
+            layouts: VecDeque::new(),
+            pending: VecDeque::new(),
+        }
+    }
+
+    /// The latest layout of the stream.
+    pub fn symbol_stream(&self) -> Option<&SymbolStream> {
+        self.layouts.back().map(|layout| &*layout.symbol_stream)
+    }
+
+    /// Takes `symbol_stream` as the stream's layout from now on, and decodes the data that was
+    /// waiting for it. A symbol stream that is republished unchanged is ignored.
+    pub fn symbols(&mut self, payload: Bytes, symbol_stream: SymbolStream) -> Vec<Event> {
+        let layout = symbol_stream.header.layout;
+        let previous = self
+            .layouts
+            .back()
+            .map(|current| current.symbol_stream.clone());
+        if previous.as_ref().map(|p| p.header.layout) == Some(layout) {
+            debug!("{}: layout {layout} republished", self.prefix);
+            return vec![];
+        }
+
+        // The PLC may go back to a layout it had before
+        self.layouts
+            .retain(|known| known.symbol_stream.header.layout != layout);
+        if self.layouts.len() == MAX_LAYOUTS {
+            self.layouts.pop_front();
+        }
+        let symbol_stream = Arc::new(symbol_stream);
+        self.layouts.push_back(Layout {
+            decoder: SampleDecoder::new(&symbol_stream),
+            symbol_stream: symbol_stream.clone(),
+        });
+
+        let mut events = vec![Event::Symbols {
+            broker: self.broker.clone(),
+            prefix: self.prefix.clone(),
+            payload,
+            symbol_stream: symbol_stream.clone(),
+        }];
+        if let Some(previous) = previous {
+            let diff = LayoutDiff::new(&previous, &symbol_stream);
+            info!(
+                "{}: layout changed from {} to {layout}: {} added, {} removed, {} retyped",
+                self.prefix,
+                previous.header.layout,
+                diff.added.len(),
+                diff.removed.len(),
+                diff.retyped.len()
+            );
+            events.push(Event::LayoutChanged {
+                broker: self.broker.clone(),
+                prefix: self.prefix.clone(),
+                previous: previous.header.layout,
+                layout,
+                online_change: symbol_stream
+                    .header
+                    .flags
+                    .contains(StreamFlags::ONLINE_CHANGE),
+                diff,
+            });
+        }
+
+        // Data for another layout may still be waiting for its symbols
+        let (ready, waiting) = self
+            .pending
+            .drain(..)
+            .partition(|data| data.header.layout == layout);
+        self.pending = waiting;
+        events.extend(ready.iter().filter_map(|data| self.decode(data)));
+        events
+    }
+
+    /// Decodes `data` with its layout, or keeps it until its layout is published.
+    pub fn data(&mut self, data: DataStream) -> Option<Event> {
+        if self.layout(data.header.layout).is_some() {
+            return self.decode(&data);
+        }
+
+        self.pending.push_back(data);
+        if self.pending.len() > self.max_pending {
+            let dropped = self.pending.pop_front().expect("pending data");
+            warn!(
+                "{}: dropping data for layout {}, its symbols have not been published",
+                self.prefix, dropped.header.layout
+            );
+        }
+        None
+    }
+
+    fn layout(&self, layout: Uuid) -> Option<&Layout> {
+        self.layouts
+            .iter()
+            .rev()
+            .find(|known| known.symbol_stream.header.layout == layout)
+    }
+
+    fn decode(&self, data: &DataStream) -> Option<Event> {
+        let decoder = &self.layout(data.header.layout)?.decoder;
+        match decoder.decode(data) {
+            Ok(samples) => Some(Event::Samples {
+                broker: self.broker.clone(),
+                prefix: self.prefix.clone(),
+                layout: decoder.layout(),
+                samples,
+            }),
+            Err(e) => {
+                warn!("{}: cannot decode data: {e}", self.prefix);
+                None
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use binary_decoder::{DataStreamWriter, NamedValue, PlcValue, Sample};
+
+    use super::*;
+    use crate::fixtures;
+
+    /// The ema symbol stream, under the layout GUID `layout`.
+    fn ema(layout: u128) -> SymbolStream {
+        let mut symbol_stream = fixtures::ema();
+        symbol_stream.header.layout = Uuid::from_u128(layout);
+        symbol_stream
+    }
+
+    /// A data stream for `symbol_stream` with one sample, in which `Main.d_intValue` is `value`.
+    fn data(symbol_stream: &SymbolStream, value: i16) -> DataStream {
+        let sample = Sample {
+            timestamp: None,
+            values: vec![NamedValue {
+                name: "Main.d_intValue".into(),
+                value: PlcValue::Int(value),
+            }],
+        };
+        let message = DataStreamWriter::new(symbol_stream)
+            .write(&[sample])
+            .unwrap();
+        DataStream::parse(&message).unwrap()
+    }
+
+    /// The layout and `Main.d_intValue` of a samples event.
+    fn value(event: &Event) -> (u128, i16) {
+        let Event::Samples {
+            layout, samples, ..
+        } = event
+        else {
+            panic!("expected samples, got {event:?}");
+        };
+        match samples[0].get("Main.d_intValue") {
+            Some(PlcValue::Int(value)) => (layout.as_u128(), *value),
+            value => panic!("unexpected value {value:?}"),
+        }
+    }
+
+    fn values(events: &[Event]) -> Vec<(u128, i16)> {
+        events
+            .iter()
+            .filter(|event| matches!(event, Event::Samples { .. }))
+            .map(value)
+            .collect()
+    }
+
+    #[test]
+    fn symbols_then_data() {
+        let mut stream = Stream::new("plant", "ema/plc", 4);
+        assert!(stream.symbol_stream().is_none());
+
+        let events = stream.symbols(Bytes::new(), ema(1));
+        assert_eq!(events.len(), 1);
+        assert!(matches!(&events[0], Event::Symbols { prefix, .. } if prefix == "ema/plc"));
+        assert_eq!(stream.symbol_stream().unwrap().header.layout.as_u128(), 1);
+
+        let event = stream.data(data(&ema(1), 7)).unwrap();
+        assert_eq!(value(&event), (1, 7));
+
+        // Republishing the same layout changes nothing
+        assert!(stream.symbols(Bytes::new(), ema(1)).is_empty());
+        assert_eq!(value(&stream.data(data(&ema(1), 8)).unwrap()), (1, 8));
+    }
+
+    #[test]
+    fn data_then_symbols() {
+        let mut stream = Stream::new("plant", "ema/plc", 4);
+        assert!(stream.data(data(&ema(1), 1)).is_none());
+        assert!(stream.data(data(&ema(1), 2)).is_none());
+
+        // The waiting data is decoded after the symbols, in the order it arrived
+        let events = stream.symbols(Bytes::new(), ema(1));
+        assert_eq!(events.len(), 3);
+        assert!(matches!(events[0], Event::Symbols { .. }));
+        assert_eq!(values(&events), [(1, 1), (1, 2)]);
+
+        // Nothing is left waiting
+        assert_eq!(values(&stream.symbols(Bytes::new(), ema(2))), []);
+    }
+
+    #[test]
+    fn data_for_another_layout() {
+        let mut stream = Stream::new("plant", "ema/plc", 4);
+        stream.symbols(Bytes::new(), ema(1));
+
+        // Data published just before an online change arrives ahead of the new symbols
+        assert!(stream.data(data(&ema(2), 20)).is_none());
+        assert_eq!(value(&stream.data(data(&ema(1), 10)).unwrap()), (1, 10));
+
+        let events = stream.symbols(Bytes::new(), ema(2));
+        assert!(matches!(events[0], Event::Symbols { .. }));
+        assert!(matches!(
+            events[1],
+            Event::LayoutChanged { previous, layout, .. }
+                if previous.as_u128() == 1 && layout.as_u128() == 2
+        ));
+        assert_eq!(values(&events), [(2, 20)]);
+    }
+
+    #[test]
+    fn max_pending() {
+        let mut stream = Stream::new("plant", "ema/plc", 2);
+        for value in 1..=3 {
+            assert!(stream.data(data(&ema(1), value)).is_none());
+        }
+
+        // The oldest data is dropped
+        let events = stream.symbols(Bytes::new(), ema(1));
+        assert_eq!(values(&events), [(1, 2), (1, 3)]);
+    }
+
+    #[test]
+    fn older_layouts() {
+        let mut stream = Stream::new("plant", "ema/plc", 4);
+        for layout in 1..=MAX_LAYOUTS as u128 {
+            stream.symbols(Bytes::new(), ema(layout));
+        }
+
+        // Data for any layout kept decodes with that layout
+        assert_eq!(value(&stream.data(data(&ema(1), 1)).unwrap()), (1, 1));
+        assert_eq!(value(&stream.data(data(&ema(5), 5)).unwrap()), (5, 5));
+
+        // One layout more evicts the oldest, whose data then waits for its symbols again
+        stream.symbols(Bytes::new(), ema(9));
+        assert!(stream.data(data(&ema(1), 1)).is_none());
+        assert_eq!(value(&stream.data(data(&ema(2), 2)).unwrap()), (2, 2));
+    }
+
+    #[test]
+    fn switch_back() {
+        let mut stream = Stream::new("plant", "ema/plc", 4);
+        for layout in 1..=MAX_LAYOUTS as u128 {
+            stream.symbols(Bytes::new(), ema(layout));
+        }
+
+        // Going back to an older layout is a layout change like any other
+        let events = stream.symbols(Bytes::new(), ema(1));
+        assert_eq!(events.len(), 2);
+        assert!(matches!(
+            events[1],
+            Event::LayoutChanged { previous, layout, .. }
+                if previous.as_u128() == MAX_LAYOUTS as u128 && layout.as_u128() == 1
+        ));
+        assert_eq!(stream.symbol_stream().unwrap().header.layout.as_u128(), 1);
+
+        // and makes it the most recent, so the next layout evicts the one after it
+        stream.symbols(Bytes::new(), ema(9));
+        assert_eq!(value(&stream.data(data(&ema(1), 1)).unwrap()), (1, 1));
+        assert!(stream.data(data(&ema(2), 2)).is_none());
+        assert_eq!(value(&stream.data(data(&ema(3), 3)).unwrap()), (3, 3));
+    }
+}
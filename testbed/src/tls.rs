@@ -0,0 +1,140 @@
+use std::fs;
+use std::path::Path;
+
+use rustls::{Certificate, ClientConfig, PrivateKey, RootCertStore};
+use rustls_pemfile::Item;
+
+use crate::config::{Secret, TlsConfig};
+use crate::BoxError;
+
+/// Builds the TLS client configuration for a broker, so that unreadable certificates and keys
+/// are reported at startup rather than on every connection attempt.
+pub fn client_config(tls: &TlsConfig) -> Result<ClientConfig, BoxError> {
+    let mut roots = RootCertStore::empty();
+    match &tls.ca {
+        Some(path) => {
+            for cert in read_certs(path)? {
+                roots
+                    .add(&cert)
+                    .map_err(|e| format!("invalid CA certificate in {}: {e}", path.display()))?;
+            }
+        }
+        None => {
+            let certs = rustls_native_certs::load_native_certs()
+                .map_err(|e| format!("cannot load the system's CA certificates: {e}"))?;
+            // System stores commonly hold a few certificates rustls does not accept
+            let certs: Vec<_> = certs.into_iter().map(|cert| cert.0).collect();
+            roots.add_parsable_certificates(&certs);
+        }
+    }
+
+    let builder = ClientConfig::builder()
+        .with_safe_defaults()
+        .with_root_certificates(roots);
+    let mut config = match (&tls.client_cert, &tls.client_key) {
+        (Some(cert), Some(key)) => builder
+            .with_client_auth_cert(read_certs(cert)?, read_key(key)?)
+            .map_err(|e| format!("invalid client certificate or key: {e}"))?,
+        _ => builder.with_no_client_auth(),
+    };
+    config.alpn_protocols = tls.alpn.iter().map(|p| p.as_bytes().to_vec()).collect();
+    Ok(config)
+}
+
+/// The certificates in the PEM file at `path`, of which there must be at least one.
+fn read_certs(path: &Path) -> Result<Vec<Certificate>, BoxError> {
+    let pem = fs::read(path).map_err(|e| format!("cannot read {}: {e}", path.display()))?;
+    let certs = rustls_pemfile::certs(&mut &pem[..])
+        .map_err(|e| format!("invalid PEM file {}: {e}", path.display()))?;
+    if certs.is_empty() {
+        return Err(format!("no certificate in {}", path.display()).into());
+    }
+    Ok(certs.into_iter().map(Certificate).collect())
+}
+
+/// The first private key in the PEM text of `key`.
+fn read_key(key: &Secret) -> Result<PrivateKey, BoxError> {
+    let pem = key.resolve()?;
+    let items = rustls_pemfile::read_all(&mut pem.as_bytes())
+        .map_err(|e| format!("invalid client key: {e}"))?;
+    items
+        .into_iter()
+        .find_map(|item| match item {
+            Item::RSAKey(key) | Item::PKCS8Key(key) | Item::ECKey(key) => Some(PrivateKey(key)),
+            _ => None,
+        })
+        .ok_or_else(|| "no private key in client key".into())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use super::*;
+
+    /// A file in `testdata`, made for the tests by a test CA.
+    fn testdata(name: &str) -> PathBuf {
+        Path::new(env!("CARGO_MANIFEST_DIR"))
+            .join("testdata")
+            .join(name)
+    }
+
+    fn tls(client_cert: Option<&str>, client_key: Option<&str>) -> TlsConfig {
+        TlsConfig {
+            ca: Some(testdata("ca.pem")),
+            client_cert: client_cert.map(testdata),
+            client_key: client_key.map(|name| Secret::File(testdata(name))),
+            alpn: vec![],
+        }
+    }
+
+    fn error(tls: &TlsConfig) -> String {
+        client_config(tls).unwrap_err().to_string()
+    }
+
+    #[test]
+    fn ca_only() {
+        let config = client_config(&tls(None, None)).unwrap();
+        assert!(!config.client_auth_cert_resolver.has_certs());
+        assert!(config.alpn_protocols.is_empty());
+    }
+
+    #[test]
+    fn client_cert() {
+        for key in ["client.key", "client.ec.key"] {
+            let config = client_config(&tls(Some("client.pem"), Some(key))).unwrap();
+            assert!(config.client_auth_cert_resolver.has_certs(), "{key}");
+        }
+
+        // Keys are secrets, and may come from the environment as well
+        let name = format!("TESTBED_CLIENT_KEY_{}", std::process::id());
+        std::env::set_var(&name, fs::read_to_string(testdata("client.key")).unwrap());
+        let mut config = tls(Some("client.pem"), None);
+        config.client_key = Some(Secret::Env(name.clone()));
+        config.alpn = vec!["mqtt".into()];
+        let config = client_config(&config).unwrap();
+        std::env::remove_var(&name);
+        assert!(config.client_auth_cert_resolver.has_certs());
+        assert_eq!(config.alpn_protocols, [b"mqtt".to_vec()]);
+    }
+
+    #[test]
+    fn invalid_files() {
+        let mut missing = tls(None, None);
+        missing.ca = Some(testdata("missing.pem"));
+        assert!(error(&missing).starts_with("cannot read "));
+
+        // A key is not a certificate
+        let mut key_as_ca = tls(None, None);
+        key_as_ca.ca = Some(testdata("client.key"));
+        assert!(error(&key_as_ca).starts_with("no certificate in "));
+        assert!(
+            error(&tls(Some("client.key"), Some("client.key"))).starts_with("no certificate in ")
+        );
+
+        assert_eq!(
+            error(&tls(Some("client.pem"), Some("client.pem"))),
+            "no private key in client key"
+        );
+    }
+}